@@ -0,0 +1,197 @@
+//! Aisles of the shop, in the order they are walked through. Ingrediants are given an aisle so the
+//! shopping list can be grouped and sorted by it.
+
+use std::{fmt::Display, sync::Arc};
+
+use iced::{
+    theme,
+    widget::{button, column as col, container, pick_list, row, scrollable, text, text_input},
+    Element, Length,
+};
+
+use crate::{
+    ingrediant::IngrediantKey, ingrediant_editor::IngrediantEditorPage, styles::delete_button,
+    Message, Model, Page, State,
+};
+
+const DEFAULT_AISLES: &[&str] = &[
+    "Produce",
+    "Bakery",
+    "Dairy",
+    "Meat & fish",
+    "Pantry",
+    "Frozen",
+    "Household",
+];
+
+pub fn default_aisles() -> Vec<Arc<str>> {
+    DEFAULT_AISLES.iter().map(|&aisle| aisle.into()).collect()
+}
+
+/// Where `category` comes in the shop. Aisles that have been removed come after the rest, and
+/// ingrediants without an aisle come last.
+pub fn rank(aisles: &[Arc<str>], category: Option<&str>) -> usize {
+    match category {
+        Some(category) => aisles
+            .iter()
+            .position(|aisle| **aisle == *category)
+            .unwrap_or(aisles.len()),
+        None => aisles.len() + 1,
+    }
+}
+
+pub fn add(model: &mut Model, name: &str) -> bool {
+    let name = name.trim();
+    if name.is_empty()
+        || model
+            .aisles
+            .iter()
+            .any(|aisle| aisle.eq_ignore_ascii_case(name))
+    {
+        return false;
+    }
+    model.aisles.push(name.into());
+    true
+}
+
+pub fn move_by(model: &mut Model, index: usize, offset: isize) {
+    let target = index.saturating_add_signed(offset);
+    if index < model.aisles.len() && target < model.aisles.len() {
+        model.aisles.swap(index, target);
+    }
+}
+
+/// Removes the aisle, ingrediants in it are left without one
+pub fn remove(model: &mut Model, index: usize) {
+    if index >= model.aisles.len() {
+        return;
+    }
+    let removed = model.aisles.remove(index);
+    for ingrediant in model.ingrediants.values_mut() {
+        if ingrediant.category.as_ref() == Some(&removed) {
+            ingrediant.category = None;
+        }
+    }
+}
+
+/// An entry of the aisle pick list, which can also be no aisle
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AisleChoice(pub Option<Arc<str>>);
+
+impl Display for AisleChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0.as_deref().unwrap_or("No aisle"))
+    }
+}
+
+pub fn choices(model: &Model) -> Vec<AisleChoice> {
+    std::iter::once(AisleChoice(None))
+        .chain(model.aisles.iter().cloned().map(Some).map(AisleChoice))
+        .collect()
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let last = state.model.aisles.len().saturating_sub(1);
+    let aisles = state.model.aisles.iter().enumerate().map(|(index, aisle)| {
+        let inner = row![
+            delete_button().on_press(Message::RemoveAisle(index)),
+            text(aisle).width(Length::Fill),
+            button("^").on_press_maybe((index > 0).then_some(Message::MoveAisle(index, -1))),
+            button("v").on_press_maybe((index < last).then_some(Message::MoveAisle(index, 1))),
+        ]
+        .align_items(iced::Alignment::Center)
+        .spacing(3)
+        .padding(2);
+        container(inner).style(theme::Container::Box).into()
+    });
+
+    let adder = row![
+        text_input("New aisle", &state.aisle_input)
+            .on_input(Message::SetAisleInput)
+            .on_submit(Message::AddAisle),
+        button("Add").on_press(Message::AddAisle),
+    ]
+    .spacing(10);
+
+    let choices = choices(&state.model);
+    let mut ingrediants: Vec<_> = state.model.ingrediants.iter().collect();
+    ingrediants.sort_by_key(|(_, ingrediant)| ingrediant.name.to_lowercase());
+    let ingrediants = ingrediants.into_iter().map(|(key, ingrediant)| {
+        let open = Message::ChangeToPage(Page::IngrediantEditorView(IngrediantEditorPage::new(
+            key,
+            &state.model,
+        )));
+        ingrediant_row(
+            key,
+            &ingrediant.name,
+            &ingrediant.category,
+            choices.clone(),
+            open,
+        )
+    });
+
+    scrollable(
+        col![
+            text("Aisles").size(30),
+            col(aisles).spacing(5),
+            adder,
+            text("Ingrediants").size(20),
+            col(ingrediants).spacing(5),
+        ]
+        .spacing(10),
+    )
+    .into()
+}
+
+fn ingrediant_row<'a>(
+    key: IngrediantKey,
+    name: &'a str,
+    category: &Option<Arc<str>>,
+    choices: Vec<AisleChoice>,
+    open: Message,
+) -> Element<'a, Message> {
+    row![
+        button(text(name))
+            .style(theme::Button::Text)
+            .on_press(open)
+            .width(Length::Fill),
+        pick_list(
+            choices,
+            Some(AisleChoice(category.clone())),
+            move |choice| Message::SetIngrediantAisle(key, choice.0)
+        ),
+    ]
+    .align_items(iced::Alignment::Center)
+    .spacing(5)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingrediant::Ingrediant;
+
+    #[test]
+    fn ranks_by_aisle_order() {
+        let aisles = default_aisles();
+        assert!(rank(&aisles, Some("Produce")) < rank(&aisles, Some("Dairy")));
+        assert_eq!(rank(&aisles, Some("Garden centre")), aisles.len());
+        assert!(rank(&aisles, Some("Garden centre")) < rank(&aisles, None));
+    }
+
+    #[test]
+    fn removing_an_aisle_clears_it() {
+        let mut model = Model::default();
+        let mut milk = Ingrediant::new("Milk".into());
+        milk.category = Some("Dairy".into());
+        let milk = model.ingrediants.push(milk);
+
+        assert!(!add(&mut model, "dairy"));
+        assert!(add(&mut model, "Deli"));
+        let dairy = model.aisles.iter().position(|a| &**a == "Dairy").unwrap();
+        remove(&mut model, dairy);
+
+        assert_eq!(model.ingrediants.get(milk).unwrap().category, None);
+        assert_eq!(model.aisles.last().map(|a| &**a), Some("Deli"));
+    }
+}
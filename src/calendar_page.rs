@@ -0,0 +1,122 @@
+use chrono::{Datelike, Months};
+use iced::{
+    theme,
+    widget::{button, column as col, row, scrollable, text, Space},
+    Command, Element, Length,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{date, page::AnyPage, Date, Message, State};
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CalendarPage {
+    /// First day of the month being shown
+    pub month: Date,
+}
+
+impl AnyPage for CalendarPage {
+    fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        self.view(state)
+    }
+
+    fn horizontal_movement(&mut self, offset: isize) -> Command<Message> {
+        self.change_month(offset);
+        Command::none()
+    }
+}
+
+impl CalendarPage {
+    pub fn new(date: Date) -> Self {
+        Self {
+            month: date::month_start(date),
+        }
+    }
+
+    pub fn change_month(&mut self, offset: isize) {
+        let months = Months::new(offset.unsigned_abs() as u32);
+        let month = if offset < 0 {
+            self.month.checked_sub_months(months)
+        } else {
+            self.month.checked_add_months(months)
+        };
+        if let Some(month) = month {
+            self.month = month;
+        }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let header = row![
+            button("<").on_press(Message::ChangeMonth(-1)),
+            text(self.month.format("%B %Y").to_string())
+                .size(30)
+                .width(Length::Fill)
+                .horizontal_alignment(iced::alignment::Horizontal::Center),
+            button(">").on_press(Message::ChangeMonth(1)),
+        ]
+        .align_items(iced::Alignment::Center);
+
+        let weekdays = row(WEEKDAYS.iter().map(|name| {
+            text(*name)
+                .width(Length::Fill)
+                .horizontal_alignment(iced::alignment::Horizontal::Center)
+                .into()
+        }));
+
+        let today = date::today();
+        let leading_blanks = self.month.weekday().num_days_from_monday() as usize;
+        let cells: Vec<Option<Date>> = std::iter::repeat_n(None, leading_blanks)
+            .chain(
+                self.month
+                    .iter_days()
+                    .take_while(|date| date.month() == self.month.month())
+                    .map(Some),
+            )
+            .collect();
+
+        let weeks = cells.chunks(7).map(|week| {
+            let mut days: Vec<Element<_>> = week
+                .iter()
+                .map(|cell| match cell {
+                    Some(date) => day_cell(state, *date, *date == today),
+                    None => Space::with_width(Length::Fill).into(),
+                })
+                .collect();
+            days.resize_with(7, || Space::with_width(Length::Fill).into());
+            row(days).spacing(3).into()
+        });
+
+        scrollable(
+            col![header, weekdays, col(weeks).spacing(3)]
+                .spacing(10)
+                .width(Length::Fill),
+        )
+        .into()
+    }
+}
+
+fn day_cell<'a>(state: &State, date: Date, is_today: bool) -> Element<'a, Message> {
+    let planned = state.model.days.get(&date).map_or(0, |day| day.meals.len());
+    let label = if planned == 0 {
+        date.day().to_string()
+    } else {
+        format!("{}\n{planned}", date.day())
+    };
+    let style = if planned > 0 {
+        theme::Button::Primary
+    } else if is_today {
+        theme::Button::Secondary
+    } else {
+        theme::Button::Text
+    };
+    button(
+        text(label)
+            .width(Length::Fill)
+            .horizontal_alignment(iced::alignment::Horizontal::Center),
+    )
+    .style(style)
+    .width(Length::Fill)
+    .on_press(Message::OpenDay(date))
+    .into()
+}
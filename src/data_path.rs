@@ -0,0 +1,94 @@
+//! Where the save file lives.
+//!
+//! By default it goes in the platform's data directory, e.g. `~/.local/share/graze/data` on Linux.
+//! This can be overridden with `--data <path>` or the `GRAZE_DATA` environment variable.
+
+use std::path::{Path, PathBuf};
+
+use directories_next::{ProjectDirs, UserDirs};
+
+const FILE_NAME: &str = "data";
+/// Where saves were kept before they moved to the data directory
+const OLD_PATH: &str = "./data";
+
+pub fn resolve() -> String {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(path) = from_args(&args).or_else(|| std::env::var("GRAZE_DATA").ok()) {
+        return path;
+    }
+
+    let Some(path) = default_path() else {
+        return OLD_PATH.to_owned();
+    };
+    move_old_save(&path);
+    path.to_string_lossy().into_owned()
+}
+
+/// Default place to export a file to, the user's documents folder if there is one
+pub fn export_path(file_name: &str) -> String {
+    UserDirs::new()
+        .and_then(|dirs| dirs.document_dir().map(|dir| dir.join(file_name)))
+        .map_or_else(
+            || file_name.to_owned(),
+            |path| path.to_string_lossy().into_owned(),
+        )
+}
+
+fn from_args(args: &[String]) -> Option<String> {
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--data" {
+            return args.next().cloned();
+        }
+        if let Some(path) = arg.strip_prefix("--data=") {
+            return Some(path.to_owned());
+        }
+    }
+    None
+}
+
+fn default_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "graze").map(|dirs| dirs.data_dir().join(FILE_NAME))
+}
+
+/// Copies a save from the working directory, where it used to be kept, into the data directory
+/// the first time the app runs with it. The old file is left alone.
+fn move_old_save(path: &Path) {
+    if path.exists() || !Path::new(OLD_PATH).is_file() {
+        return;
+    }
+    let copied = path
+        .parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| std::fs::copy(OLD_PATH, path));
+    match copied {
+        Ok(_) => eprintln!("Copied save file from {OLD_PATH} to {}", path.display()),
+        Err(err) => eprintln!(
+            "Couldn't copy save file from {OLD_PATH} to {}: {err}",
+            path.display()
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| (*arg).to_owned()).collect()
+    }
+
+    #[test]
+    fn data_flag() {
+        assert_eq!(from_args(&args(&[])), None);
+        assert_eq!(
+            from_args(&args(&["--data", "/tmp/plan"])),
+            Some("/tmp/plan".to_owned())
+        );
+        assert_eq!(
+            from_args(&args(&["--data=/tmp/plan"])),
+            Some("/tmp/plan".to_owned())
+        );
+        assert_eq!(from_args(&args(&["--data"])), None);
+    }
+}
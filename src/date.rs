@@ -0,0 +1,46 @@
+use std::ops::Range;
+
+use chrono::{Datelike, Days, Local};
+
+use crate::Date;
+
+pub fn today() -> Date {
+    Local::now().date_naive()
+}
+
+/// The monday of the ISO week `date` is in
+pub fn week_start(date: Date) -> Date {
+    date.week(chrono::Weekday::Mon).first_day()
+}
+
+/// The first day of the month `date` is in
+pub fn month_start(date: Date) -> Date {
+    date.with_day(1).unwrap_or(date)
+}
+
+pub fn add_days(date: Date, days: u64) -> Date {
+    date.checked_add_days(Days::new(days)).unwrap_or(Date::MAX)
+}
+
+/// The `weeks` weeks starting with the current one
+pub fn weeks_from_now(weeks: u64) -> Range<Date> {
+    let start = week_start(today());
+    start..add_days(start, weeks * 7)
+}
+
+pub fn days(range: Range<Date>) -> impl Iterator<Item = Date> {
+    range
+        .start
+        .iter_days()
+        .take_while(move |date| *date < range.end)
+}
+
+/// Short label for a day e.g. "Mon 14 Oct"
+pub fn short_label(date: Date) -> String {
+    date.format("%a %-d %b").to_string()
+}
+
+/// Long label for a day e.g. "Monday 14 October 2024"
+pub fn long_label(date: Date) -> String {
+    date.format("%A %-d %B %Y").to_string()
+}
@@ -1,8 +1,9 @@
 use crate::{
-    generational_map::GenerationalMap, meal::Meal, picker::PickerState, Date, Message, State,
+    generational_map::GenerationalMap, meal::Meal, nutrition, page::AnyPage, picker::PickerState,
+    Date, Message, State,
 };
 use iced::{
-    widget::{button, column as col, scrollable, text},
+    widget::{button, column as col, row, scrollable, text},
     Command, Element, Length,
 };
 use iced_aw::{floating_element, modal};
@@ -18,7 +19,7 @@ pub struct DayPage {
 }
 
 #[derive(Clone)]
-struct MessageConverter {
+pub struct MessageConverter {
     date: Date,
 }
 
@@ -28,6 +29,42 @@ impl crate::picker::NameToMessageConverter for MessageConverter {
     }
 }
 
+impl AnyPage for DayPage {
+    fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        self.view(state)
+    }
+
+    fn vertical_movement(&mut self, ammount: isize) -> Command<Message> {
+        if let Some(picker) = self.meal_picker.as_mut() {
+            picker.vertical_movement(ammount);
+        }
+        Command::none()
+    }
+
+    fn picker_input(&mut self, input: String) -> Command<Message> {
+        if let Some(picker) = self.meal_picker.as_mut() {
+            picker.input(input);
+        }
+        Command::none()
+    }
+
+    fn close_popup(&mut self) -> Command<Message> {
+        self.close_picker();
+        Command::none()
+    }
+
+    fn open_picker(&mut self, state: &State) -> Command<Message> {
+        self.open_meal_picker(&state.model.meals)
+    }
+
+    fn on_tab(&mut self, _: bool) -> Command<Message> {
+        if let Some(picker) = self.meal_picker.as_mut() {
+            picker.fill_input();
+        }
+        Command::none()
+    }
+}
+
 impl DayPage {
     pub fn new(date: Date) -> Self {
         Self {
@@ -52,27 +89,42 @@ impl DayPage {
     }
 
     pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
-        let Some(day) = state.days.get(&self.date) else {
+        let Some(day) = state.model.days.get(&self.date) else {
             return col!["Day not found"].into();
         };
 
-        let days = day.meals.iter().enumerate().flat_map(|(i, id)| {
-            state.meals.get(*id).map(|meal| {
-                crate::meal::meal_row_view(
-                    meal,
-                    *id,
-                    Message::RemoveMealFromDay {
-                        date: self.date,
+        let days = day.meals.iter().enumerate().flat_map(|(i, planned)| {
+            state.model.meals.get(planned.meal_id).map(|meal| {
+                let date = self.date;
+                let portions = iced_aw::number_input(planned.portions, 99, move |portions| {
+                    Message::SetPlannedPortions {
+                        date,
                         index: i,
-                    },
-                )
+                        portions,
+                    }
+                })
+                .min(1);
+                let meal_row = crate::meal::meal_row_view(
+                    meal,
+                    planned.meal_id,
+                    Message::RemoveMealFromDay { date, index: i },
+                );
+                row![portions, meal_row]
+                    .spacing(5)
+                    .align_items(iced::Alignment::Center)
+                    .into()
             })
         });
 
         let main_content = col![
-            text(format!("Day {}", self.date)).size(30),
+            text(crate::date::long_label(self.date)).size(30),
             // adder_widget,
             col(days).spacing(10),
+            text("Nutrition").size(20),
+            nutrition::totals_view(
+                nutrition::day_totals(&state.model, self.date),
+                Some(&state.model.nutrition_targets)
+            ),
         ]
         .width(Length::Fill)
         .height(Length::Fill)
@@ -0,0 +1,283 @@
+//! A reference table of foods and their nutrients, imported from a food composition CSV such as
+//! a USDA FoodData Central or national food composition table export. Ingrediants can be linked to
+//! a food to take its nutrients.
+//!
+//! Columns are found by their headings, so any table with a food name column and per 100 g
+//! nutrient columns can be read. The imported table is kept in its own file next to the save file
+//! and only read when a food is looked up.
+
+use std::{io, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    nutrition::{Nutrient, Nutrients},
+    save_file,
+};
+
+const TABLE_FILE_NAME: &str = "foods.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Food {
+    pub name: Arc<str>,
+    /// In 100 g of it
+    pub nutrients: Nutrients,
+}
+
+/// Whether `heading` names a column of `nutrient`
+fn is_column_of(nutrient: Nutrient, heading: &str) -> bool {
+    match nutrient {
+        Nutrient::Energy => {
+            ["energy", "calorie", "kcal"]
+                .iter()
+                .any(|word| heading.contains(word))
+                && !heading.contains("kj")
+        }
+        Nutrient::Protein => heading.contains("protein"),
+        Nutrient::Fat => {
+            (heading.contains("fat") || heading.contains("lipid")) && !heading.contains("fatty")
+        }
+        Nutrient::Carbs => heading.contains("carbohydrate") || heading.contains("carbs"),
+        Nutrient::Fibre => heading.contains("fiber") || heading.contains("fibre"),
+    }
+}
+
+/// The column holding food names. Headings saying "name" or "description" are preferred, as
+/// tables often have columns like "Food code" or "Food group" as well.
+fn name_column(headings: &[String]) -> Option<usize> {
+    headings
+        .iter()
+        .position(|heading| heading.contains("name") || heading.contains("description"))
+        .or_else(|| headings.iter().position(|heading| heading == "food"))
+}
+
+/// The column holding `nutrient`. Fat has columns for its kinds, like saturated fat, so a total
+/// or plain fat column is preferred.
+fn column_of(nutrient: Nutrient, headings: &[String]) -> Option<usize> {
+    let preferred = |heading: &String| {
+        nutrient != Nutrient::Fat
+            || !heading.contains("saturated")
+                && (heading.contains("total")
+                    || heading.split(|c: char| !c.is_alphabetic()).next() == Some("fat"))
+    };
+    let columns = || {
+        headings
+            .iter()
+            .enumerate()
+            .filter(|(_, heading)| is_column_of(nutrient, heading))
+    };
+    columns()
+        .find(|(_, heading)| preferred(heading))
+        .or_else(|| columns().next())
+        .map(|(column, _)| column)
+}
+
+/// Splits CSV `text` into records. Fields may be quoted, with `""` for a quote inside them.
+fn records(text: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            c if c == delimiter && !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Reads foods out of a CSV table. The delimiter is whichever of comma, semicolon and tab the
+/// heading row has most of. Values that aren't numbers, like "Tr" for trace, count as 0.
+pub fn parse(text: &str) -> Result<Vec<Food>, String> {
+    let text = text.trim_start_matches('\u{feff}');
+    let heading_row = text.lines().next().unwrap_or_default();
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|delimiter| heading_row.matches(*delimiter).count())
+        .unwrap_or(',');
+
+    let mut records = records(text, delimiter).into_iter();
+    let headings: Vec<String> = records
+        .next()
+        .ok_or("the file is empty")?
+        .iter()
+        .map(|heading| heading.trim().to_lowercase())
+        .collect();
+    let name_column = name_column(&headings).ok_or("no food name column found")?;
+    let columns: Vec<(Nutrient, usize)> = Nutrient::ALL
+        .into_iter()
+        .filter_map(|nutrient| Some((nutrient, column_of(nutrient, &headings)?)))
+        .collect();
+    if columns.is_empty() {
+        return Err("no nutrient columns found".to_owned());
+    }
+
+    Ok(records
+        .filter_map(|record| {
+            let name = record.get(name_column)?.trim();
+            if name.is_empty() {
+                return None;
+            }
+            let mut nutrients = Nutrients::default();
+            for &(nutrient, column) in &columns {
+                *nutrients.get_mut(nutrient) = record
+                    .get(column)
+                    .and_then(|value| value.trim().replace(',', ".").parse().ok())
+                    .unwrap_or_default();
+            }
+            Some(Food {
+                name: name.into(),
+                nutrients,
+            })
+        })
+        .collect())
+}
+
+pub fn find<'a>(foods: &'a [Food], name: &str) -> Option<&'a Food> {
+    foods.iter().find(|food| *food.name == *name)
+}
+
+pub async fn read(path: String) -> Result<Vec<Food>, String> {
+    let text = async_std::fs::read_to_string(&path)
+        .await
+        .map_err(|err| format!("couldn't read {path}: {err}"))?;
+    parse(&text).map_err(|err| format!("couldn't read {path}: {err}"))
+}
+
+/// Where the imported table is kept, next to the save file at `save_path`
+pub fn table_path(save_path: &str) -> String {
+    Path::new(save_path)
+        .with_file_name(TABLE_FILE_NAME)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Reads the CSV table at `csv_path` and keeps it at `table_path` in place of the table there. A
+/// table without any foods is refused so the current one isn't lost.
+pub async fn import(csv_path: String, table_path: String) -> Result<Vec<Food>, String> {
+    let foods = read(csv_path.clone()).await?;
+    if foods.is_empty() {
+        return Err(format!("couldn't import {csv_path}: no foods found"));
+    }
+    let bytes = serde_json::to_vec(&foods).map_err(|err| err.to_string())?;
+    let now = chrono::Local::now().naive_local();
+    save_file::write(&table_path, &bytes, 0, now)
+        .await
+        .map_err(|err| format!("couldn't write {table_path}: {err}"))?;
+    Ok(foods)
+}
+
+/// The table kept at `table_path`
+pub async fn load(table_path: String) -> Result<Vec<Food>, String> {
+    let bytes = match async_std::fs::read(&table_path).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err("no food table has been imported yet, it can be on the Library page".into())
+        }
+        Err(err) => return Err(format!("couldn't read {table_path}: {err}")),
+    };
+    serde_json::from_slice(&bytes).map_err(|err| format!("couldn't read {table_path}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_comma_separated() {
+        let csv = "\u{feff}FDC ID,Description,\"Energy (kJ)\",Energy (kcal),Protein (g),\
+            Total lipid (fat) (g),\"Fatty acids, total saturated (g)\",\
+            \"Carbohydrate, by difference (g)\",\"Fiber, total dietary (g)\"\r\n\
+            1,\"Oats, rolled\",1500,379,13.2,6.5,1.1,67.7,10.1\r\n\
+            2,Salt,0,0,0,0,0,0,\r\n";
+        let foods = parse(csv).unwrap();
+        assert_eq!(foods.len(), 2);
+        assert_eq!(&*foods[0].name, "Oats, rolled");
+        assert_eq!(
+            foods[0].nutrients,
+            Nutrients {
+                energy: 379.0,
+                protein: 13.2,
+                fat: 6.5,
+                carbs: 67.7,
+                fibre: 10.1,
+            }
+        );
+        assert_eq!(foods[1].nutrients.fibre, 0.0);
+    }
+
+    #[test]
+    fn reads_semicolon_separated() {
+        let csv = "Food name;Energy, kcal;Protein;Fibre\nApple;52;0,3;Tr\n";
+        let foods = parse(csv).unwrap();
+        assert_eq!(
+            foods,
+            [Food {
+                name: "Apple".into(),
+                nutrients: Nutrients {
+                    energy: 52.0,
+                    protein: 0.3,
+                    ..Nutrients::default()
+                },
+            }]
+        );
+        assert!(parse("Name;Price\nApple;1\n").is_err());
+    }
+
+    #[test]
+    fn prefers_name_and_total_columns() {
+        let csv = "Food code;Food name;Saturated fat (g);Fat (g);Protein (g)\n\
+            A01;Butter;51,4;81,1;0,9\n";
+        let foods = parse(csv).unwrap();
+        assert_eq!(&*foods[0].name, "Butter");
+        assert_eq!(foods[0].nutrients.fat, 81.1);
+
+        let csv = "Food,Saturated fat,\"Fat, total\"\nButter,51.4,81.1\n";
+        let foods = parse(csv).unwrap();
+        assert_eq!(&*foods[0].name, "Butter");
+        assert_eq!(foods[0].nutrients.fat, 81.1);
+    }
+
+    #[test]
+    fn keeps_table_beside_save() {
+        let dir = std::env::temp_dir().join(format!("graze-foods-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let csv = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            path.to_string_lossy().into_owned()
+        };
+        let table = table_path(&dir.join("data").to_string_lossy());
+        assert!(table.ends_with(TABLE_FILE_NAME));
+
+        async_std::task::block_on(async {
+            assert!(load(table.clone()).await.is_err());
+            let apples = csv("apples.csv", "Name,Energy (kcal)\nApple,52\n");
+            let imported = import(apples, table.clone()).await.unwrap();
+            assert_eq!(load(table.clone()).await.unwrap(), imported);
+
+            // A table without foods doesn't replace the one there
+            let empty = csv("empty.csv", "Name,Energy (kcal)\n");
+            assert!(import(empty, table.clone()).await.is_err());
+            assert_eq!(load(table.clone()).await.unwrap(), imported);
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -178,6 +178,18 @@ impl<T> GenerationalMap<T> {
             .filter_map(|(_index, (_generation, wraped_item))| wraped_item.as_mut())
     }
 
+    /// Converts every item while keeping them in the same slots, so existing keys stay valid
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> GenerationalMap<U> {
+        GenerationalMap {
+            data: self
+                .data
+                .into_iter()
+                .map(|(generation, item)| (generation, item.map(&mut f)))
+                .collect(),
+            free: self.free,
+        }
+    }
+
     #[allow(dead_code)]
     const fn new() -> Self {
         Self {
@@ -316,6 +328,30 @@ mod tests {
         assert_eq!(subject.remove(second), Some(30));
     }
 
+    #[test]
+    fn map() {
+        let mut subject = GenerationalMap::new();
+        let first = subject.push(1);
+        let second = subject.push(2);
+        subject.remove(first);
+        let subject = subject.map(|item| item * 10);
+        assert_eq!(
+            subject,
+            GenerationalMap {
+                data: vec![(0, None), (0, Some(20))],
+                free: vec![0]
+            }
+        );
+        assert_eq!(
+            subject.get(GenerationalKey {
+                index: second.index,
+                generation: second.generation,
+                marker: PhantomData
+            }),
+            Some(&20)
+        );
+    }
+
     #[test]
     fn iter() {
         let mut subject = GenerationalMap::new();
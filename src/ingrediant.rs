@@ -1,12 +1,143 @@
-use std::sync::Arc;
+use std::{fmt::Display, sync::Arc};
 
 use serde::{Deserialize, Serialize};
 
-use crate::{generational_map::GenerationalKey, Unit};
+use crate::{
+    generational_map::GenerationalKey,
+    nutrition::Nutrition,
+    stable_id::StableId,
+    unit::{units_for, Conversions, SpoonRegion, UnitSystem, UNITS},
+    Unit,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Ingrediant {
+    pub id: StableId,
     pub name: Arc<str>,
+    /// The aisle it is found in, one of `Model::aisles`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<Arc<str>>,
+    /// Grams per millilitre, so weights and volumes of it can be added up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub density: Option<f64>,
+    /// Grams one piece weighs, so counts and weights of it can be added up
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub piece_weight: Option<f64>,
+    /// Units of its own, referred to by `Unit::Custom`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub units: Vec<CustomUnit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nutrition: Option<Nutrition>,
+    /// The food in the food table its nutrition was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub food: Option<Arc<str>>,
+}
+
+/// A unit only one ingrediant comes in, like a clove of garlic or a can of tomatoes
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomUnit {
+    pub name: Arc<str>,
+    /// How many of `base` one is, so it can be added up with other amounts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<f64>,
+    /// Grams or millilitres
+    #[serde(default)]
+    pub base: Unit,
+}
+
+impl CustomUnit {
+    pub fn new(name: Arc<str>) -> Self {
+        Self {
+            name,
+            size: None,
+            base: Unit::default(),
+        }
+    }
+}
+
+impl Ingrediant {
+    pub fn new(name: Arc<str>) -> Self {
+        Self {
+            id: StableId::new(),
+            name,
+            category: None,
+            density: None,
+            piece_weight: None,
+            units: Vec::new(),
+            nutrition: None,
+            food: None,
+        }
+    }
+
+    /// Its own unit called `name`
+    pub fn custom_unit(&self, name: &str) -> Option<Unit> {
+        let index = self
+            .units
+            .iter()
+            .position(|unit| unit.name.eq_ignore_ascii_case(name))?;
+        Some(Unit::Custom(index.try_into().ok()?))
+    }
+
+    /// Whether one of its own units can be called `name`. It can't be empty, the name of a
+    /// built in unit or that of another of its units, other than the one at `except`.
+    pub fn valid_unit_name(&self, name: &str, except: Option<usize>) -> bool {
+        let name = name.trim();
+        !name.is_empty()
+            && !self
+                .units
+                .iter()
+                .enumerate()
+                .any(|(index, unit)| Some(index) != except && unit.name.eq_ignore_ascii_case(name))
+            && !UNITS
+                .iter()
+                .any(|unit| unit.abreviation().eq_ignore_ascii_case(name))
+    }
+
+    /// Adds a unit of its own, unless the name isn't valid or there are too many
+    pub fn add_unit(&mut self, name: &str) -> Option<Unit> {
+        let name = name.trim();
+        if !self.valid_unit_name(name, None) {
+            return None;
+        }
+        let unit = Unit::Custom(self.units.len().try_into().ok()?);
+        self.units.push(CustomUnit::new(name.into()));
+        Some(unit)
+    }
+
+    pub fn unit_label(&self, unit: Unit) -> &str {
+        match unit {
+            Unit::Custom(index) => self
+                .units
+                .get(usize::from(index))
+                .map_or(unit.abreviation(), |custom| &custom.name),
+            _ => unit.abreviation(),
+        }
+    }
+
+    /// `quantity` of `unit` in grams or millilitres if it is one of its own units of known size,
+    /// otherwise left as it is
+    pub fn resolve(&self, quantity: f64, unit: Unit) -> (f64, Unit) {
+        let Unit::Custom(index) = unit else {
+            return (quantity, unit);
+        };
+        match self.units.get(usize::from(index)) {
+            Some(CustomUnit {
+                size: Some(size),
+                base,
+                ..
+            }) => (quantity * size, *base),
+            _ => (quantity, unit),
+        }
+    }
+
+    /// How its units relate, with tablespoons the size of `spoons`
+    pub const fn conversions(&self, spoons: SpoonRegion) -> Conversions {
+        Conversions {
+            density: self.density,
+            piece_weight: self.piece_weight,
+            spoons,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, PartialOrd)]
@@ -16,3 +147,67 @@ pub struct IngrediantQuantity {
 }
 
 pub type IngrediantKey = GenerationalKey<Ingrediant>;
+
+/// A unit offered when entering an amount of an ingrediant, shown by the name it has there
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitChoice {
+    pub unit: Unit,
+    label: Arc<str>,
+}
+
+impl UnitChoice {
+    pub fn new(ingrediant: Option<&Ingrediant>, unit: Unit) -> Self {
+        let label = ingrediant.map_or(unit.abreviation(), |ingrediant| ingrediant.unit_label(unit));
+        Self {
+            unit,
+            label: label.into(),
+        }
+    }
+}
+
+impl Display for UnitChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.label)
+    }
+}
+
+/// The units of `system` followed by the ingrediant's own
+pub fn unit_choices(
+    ingrediant: Option<&Ingrediant>,
+    system: UnitSystem,
+    current: Unit,
+) -> Vec<UnitChoice> {
+    let custom = ingrediant.map_or(0, |ingrediant| ingrediant.units.len());
+    units_for(system, current)
+        .into_iter()
+        .chain((0..custom).filter_map(|index| Some(Unit::Custom(index.try_into().ok()?))))
+        .map(|unit| UnitChoice::new(ingrediant, unit))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::SolidUnit;
+
+    #[test]
+    fn resolves_sized_units() {
+        let mut garlic = Ingrediant::new("Garlic".into());
+        let clove = garlic.add_unit("clove").unwrap();
+        let bulb = garlic.add_unit("bulb").unwrap();
+        assert_eq!(garlic.add_unit("Clove"), None);
+        assert_eq!(garlic.add_unit("g"), None);
+        assert!(garlic.valid_unit_name("Clove ", Some(0)));
+        assert!(!garlic.valid_unit_name("clove", Some(1)));
+        assert!(!garlic.valid_unit_name("KG", Some(0)));
+        assert!(!garlic.valid_unit_name(" ", Some(0)));
+        garlic.units[0].size = Some(5.0);
+
+        assert_eq!(garlic.unit_label(bulb), "bulb");
+        assert_eq!(
+            garlic.resolve(3.0, clove),
+            (15.0, Unit::Solid(SolidUnit::Grams))
+        );
+        assert_eq!(garlic.resolve(2.0, bulb), (2.0, bulb));
+    }
+}
@@ -0,0 +1,783 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use iced::{
+    widget::{button, column as col, pick_list, row, scrollable, text, text_input},
+    Command, Element, Length,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aisles,
+    food_table::{self, Food},
+    ingrediant::{
+        unit_choices, CustomUnit, Ingrediant, IngrediantKey, IngrediantQuantity, UnitChoice,
+    },
+    nutrition::{Nutrient, Nutrition},
+    page::AnyPage,
+    picker::{NameToMessageConverter, PickerState},
+    styles::delete_button,
+    unit::{LiquidUnit, SolidUnit, Unit},
+    Message, Model, State,
+};
+
+/// What the size of a custom unit can be given in
+const UNIT_BASES: [Unit; 2] = [
+    Unit::Solid(SolidUnit::Grams),
+    Unit::Liquid(LiquidUnit::MilliLiters),
+];
+
+/// Edits an ingrediant's own details, shared by every meal it is in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IngrediantEditorPage {
+    pub ingrediant_id: IngrediantKey,
+    /// Kept as typed so a half written number isn't lost
+    density_input: String,
+    piece_weight_input: String,
+    #[serde(default)]
+    unit_input: String,
+    /// Names of the ingrediant's own units, as typed. A name is only used once it is valid.
+    #[serde(default)]
+    unit_name_inputs: Vec<String>,
+    /// Sizes of the ingrediant's own units, as typed
+    #[serde(default)]
+    unit_size_inputs: Vec<String>,
+    /// Nutrients as typed, an ingrediant with none filled in has no nutrition
+    #[serde(default)]
+    nutrient_inputs: BTreeMap<Nutrient, String>,
+    #[serde(default)]
+    nutrition_per_input: String,
+    #[serde(default)]
+    nutrition_per_unit: Unit,
+    #[serde(skip)]
+    food_picker: Option<PickerState<FoodConverter>>,
+}
+
+#[derive(Clone)]
+pub struct FoodConverter;
+
+impl NameToMessageConverter for FoodConverter {
+    fn convert(&self, name: Arc<str>) -> Message {
+        Message::FoodPicked(name)
+    }
+
+    fn takes_new_names(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum IngrediantEdit {
+    Name(String),
+    Density(String),
+    PieceWeight(String),
+    UnitInput(String),
+    AddUnit,
+    UnitName(usize, String),
+    UnitSize(usize, String),
+    UnitBase(usize, Unit),
+    RemoveUnit(usize),
+    Nutrient(Nutrient, String),
+    NutritionPer(String),
+    NutritionPerUnit(Unit),
+}
+
+impl AnyPage for IngrediantEditorPage {
+    fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        self.view(state)
+    }
+
+    fn vertical_movement(&mut self, ammount: isize) -> Command<Message> {
+        if let Some(picker) = self.food_picker.as_mut() {
+            picker.vertical_movement(ammount);
+        }
+        Command::none()
+    }
+
+    fn picker_input(&mut self, input: String) -> Command<Message> {
+        if let Some(picker) = self.food_picker.as_mut() {
+            picker.input(input);
+        }
+        Command::none()
+    }
+
+    fn close_popup(&mut self) -> Command<Message> {
+        self.close_picker();
+        Command::none()
+    }
+
+    fn open_picker(&mut self, state: &State) -> Command<Message> {
+        self.open_food_picker(state.foods.as_deref().unwrap_or_default())
+    }
+
+    fn on_tab(&mut self, _: bool) -> Command<Message> {
+        if let Some(picker) = self.food_picker.as_mut() {
+            picker.fill_input();
+        }
+        Command::none()
+    }
+}
+
+/// An empty input clears the value, anything that isn't a positive number is ignored
+fn optional_number(input: &str) -> Option<Option<f64>> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(None);
+    }
+    input
+        .parse::<f64>()
+        .ok()
+        .filter(|number| *number > 0.0)
+        .map(Some)
+}
+
+/// An empty input is none of it, anything that isn't a number of at least 0 is ignored
+fn nutrient_value(input: &str) -> Option<f64> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(0.0);
+    }
+    input.parse::<f64>().ok().filter(|number| *number >= 0.0)
+}
+
+fn number_label(number: Option<f64>) -> String {
+    number.map(|number| number.to_string()).unwrap_or_default()
+}
+
+/// Moves `ammount` off the removed unit at `index`, into its size, and down from the units after it
+fn without_unit(ammount: &mut IngrediantQuantity, index: usize, removed: &CustomUnit) {
+    match ammount.unit {
+        Unit::Custom(at) if usize::from(at) == index => {
+            if let Some(size) = removed.size {
+                ammount.quantity *= size;
+                ammount.unit = removed.base;
+            }
+        }
+        Unit::Custom(at) if usize::from(at) > index => ammount.unit = Unit::Custom(at - 1),
+        _ => {}
+    }
+}
+
+/// Like [`without_unit`] for what nutrition is given per, which goes back to 100 g if it was an
+/// unsized unit
+fn per_without_unit(per: &mut IngrediantQuantity, index: usize, removed: &CustomUnit) {
+    if per.unit == Unit::Custom(index as u8) && removed.size.is_none() {
+        *per = Nutrition::default().per;
+    } else {
+        without_unit(per, index, removed);
+    }
+}
+
+/// How many amounts in meals and the pantry are given in the ingrediant's own unit at `index`
+pub fn unit_uses(model: &Model, ingrediant_id: IngrediantKey, index: usize) -> usize {
+    let unit = Unit::Custom(index as u8);
+    let in_meals = model
+        .meals
+        .values()
+        .filter_map(|meal| meal.ingrediants.get(&ingrediant_id))
+        .filter(|ammount| ammount.unit == unit)
+        .count();
+    let in_pantry = model
+        .pantry
+        .get(&ingrediant_id)
+        .filter(|ammount| ammount.unit == unit)
+        .is_some();
+    in_meals + usize::from(in_pantry)
+}
+
+/// Removes the ingrediant's own unit at `index`, returning whether it was. Amounts in it become its
+/// size and amounts in the units after it are moved down to their new index. A unit without a size
+/// can't be converted, so it is kept while anything uses it. Nutrition given per an unsized unit
+/// goes back to being per 100 g.
+pub fn remove_unit(model: &mut Model, ingrediant_id: IngrediantKey, index: usize) -> bool {
+    let in_use = unit_uses(model, ingrediant_id, index) > 0;
+    let Some(ingrediant) = model.ingrediants.get_mut(ingrediant_id) else {
+        return false;
+    };
+    match ingrediant.units.get(index) {
+        Some(unit) if unit.size.is_some() || !in_use => {}
+        _ => return false,
+    }
+    let removed = ingrediant.units.remove(index);
+    let renumber = |ammount: &mut IngrediantQuantity| without_unit(ammount, index, &removed);
+    if let Some(nutrition) = &mut ingrediant.nutrition {
+        per_without_unit(&mut nutrition.per, index, &removed);
+    }
+    for meal in model.meals.values_mut() {
+        if let Some(ammount) = meal.ingrediants.get_mut(&ingrediant_id) {
+            renumber(ammount);
+        }
+    }
+    if let Some(ammount) = model.pantry.get_mut(&ingrediant_id) {
+        renumber(ammount);
+    }
+    true
+}
+
+impl IngrediantEditorPage {
+    pub fn new(ingrediant_id: IngrediantKey, model: &Model) -> Self {
+        let ingrediant = model.ingrediants.get(ingrediant_id);
+        let nutrition = ingrediant.and_then(|ing| ing.nutrition.as_ref());
+        let per = nutrition
+            .map(|nutrition| nutrition.per.clone())
+            .unwrap_or_else(|| Nutrition::default().per);
+        Self {
+            ingrediant_id,
+            density_input: number_label(ingrediant.and_then(|ing| ing.density)),
+            piece_weight_input: number_label(ingrediant.and_then(|ing| ing.piece_weight)),
+            unit_input: String::new(),
+            unit_name_inputs: ingrediant
+                .map(|ing| ing.units.iter().map(|unit| unit.name.to_string()).collect())
+                .unwrap_or_default(),
+            unit_size_inputs: ingrediant
+                .map(|ing| {
+                    ing.units
+                        .iter()
+                        .map(|unit| number_label(unit.size))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            nutrient_inputs: nutrition
+                .map(|nutrition| {
+                    Nutrient::ALL
+                        .into_iter()
+                        .map(|nutrient| (nutrient, nutrition.nutrients.get(nutrient).to_string()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            nutrition_per_input: per.quantity.to_string(),
+            nutrition_per_unit: per.unit,
+            food_picker: None,
+        }
+    }
+
+    pub fn open_food_picker(&mut self, foods: &[Food]) -> Command<Message> {
+        if foods.is_empty() {
+            return Command::none();
+        }
+        let picker = self.food_picker.get_or_insert_with(|| {
+            PickerState::new(
+                foods.iter().map(|food| food.name.clone()).collect(),
+                FoodConverter,
+            )
+        });
+        text_input::focus(picker.input_feild_id.clone())
+    }
+
+    pub fn close_picker(&mut self) {
+        self.food_picker = None;
+    }
+
+    /// Links the ingrediant to the food called `name` and takes its nutrients, which are per 100 g
+    pub fn link_food(&mut self, model: &mut Model, foods: &[Food], name: &str) {
+        let Some(food) = food_table::find(foods, name).cloned() else {
+            return;
+        };
+        let Some(ingrediant) = model.ingrediants.get_mut(self.ingrediant_id) else {
+            return;
+        };
+        ingrediant.nutrition = Some(Nutrition {
+            nutrients: food.nutrients,
+            ..Nutrition::default()
+        });
+        ingrediant.food = Some(food.name);
+        *self = Self::new(self.ingrediant_id, model);
+    }
+
+    /// Sets the ingrediant's nutrition from what has been typed so far
+    fn update_nutrition(&self, ingrediant: &mut Ingrediant) {
+        if self
+            .nutrient_inputs
+            .values()
+            .all(|input| input.trim().is_empty())
+        {
+            ingrediant.nutrition = None;
+            return;
+        }
+        let nutrition = ingrediant.nutrition.get_or_insert_with(Nutrition::default);
+        for (nutrient, input) in &self.nutrient_inputs {
+            if let Some(value) = nutrient_value(input) {
+                *nutrition.nutrients.get_mut(*nutrient) = value;
+            }
+        }
+        if let Some(Some(per)) = optional_number(&self.nutrition_per_input) {
+            nutrition.per.quantity = per;
+        }
+        nutrition.per.unit = self.nutrition_per_unit;
+    }
+
+    pub fn edit(&mut self, model: &mut Model, edit: IngrediantEdit) {
+        if let IngrediantEdit::RemoveUnit(index) = edit {
+            let removed = model
+                .ingrediants
+                .get(self.ingrediant_id)
+                .and_then(|ingrediant| ingrediant.units.get(index))
+                .cloned();
+            if !remove_unit(model, self.ingrediant_id, index) {
+                return;
+            }
+            let ingrediant = model.ingrediants.get(self.ingrediant_id);
+            match (ingrediant.and_then(|ing| ing.nutrition.as_ref()), removed) {
+                (Some(nutrition), _) => {
+                    self.nutrition_per_input = nutrition.per.quantity.to_string();
+                    self.nutrition_per_unit = nutrition.per.unit;
+                }
+                // Without nutrition yet only the inputs hold what it will be given per
+                (None, Some(removed)) => {
+                    let quantity = self.nutrition_per_input.trim().parse().ok();
+                    let mut per = IngrediantQuantity {
+                        quantity: quantity.unwrap_or(Nutrition::default().per.quantity),
+                        unit: self.nutrition_per_unit,
+                    };
+                    per_without_unit(&mut per, index, &removed);
+                    if per.unit != self.nutrition_per_unit {
+                        self.nutrition_per_input = per.quantity.to_string();
+                        self.nutrition_per_unit = per.unit;
+                    }
+                }
+                (None, None) => {}
+            }
+            if index < self.unit_name_inputs.len() {
+                self.unit_name_inputs.remove(index);
+            }
+            if index < self.unit_size_inputs.len() {
+                self.unit_size_inputs.remove(index);
+            }
+            return;
+        }
+        let Some(ingrediant) = model.ingrediants.get_mut(self.ingrediant_id) else {
+            return;
+        };
+        match edit {
+            IngrediantEdit::Name(name) => ingrediant.name = name.into(),
+            IngrediantEdit::Density(input) => {
+                if let Some(density) = optional_number(&input) {
+                    ingrediant.density = density;
+                }
+                self.density_input = input;
+            }
+            IngrediantEdit::PieceWeight(input) => {
+                if let Some(piece_weight) = optional_number(&input) {
+                    ingrediant.piece_weight = piece_weight;
+                }
+                self.piece_weight_input = input;
+            }
+            IngrediantEdit::UnitInput(input) => self.unit_input = input,
+            IngrediantEdit::AddUnit => {
+                if ingrediant.add_unit(&self.unit_input).is_some() {
+                    self.unit_name_inputs
+                        .push(self.unit_input.trim().to_owned());
+                    self.unit_input.clear();
+                    self.unit_size_inputs.push(String::new());
+                }
+            }
+            IngrediantEdit::UnitName(index, input) => {
+                if index >= ingrediant.units.len() {
+                    return;
+                }
+                if ingrediant.valid_unit_name(&input, Some(index)) {
+                    ingrediant.units[index].name = input.trim().into();
+                }
+                if let Some(name_input) = self.unit_name_inputs.get_mut(index) {
+                    *name_input = input;
+                }
+            }
+            IngrediantEdit::UnitSize(index, input) => {
+                let (Some(unit), Some(size_input)) = (
+                    ingrediant.units.get_mut(index),
+                    self.unit_size_inputs.get_mut(index),
+                ) else {
+                    return;
+                };
+                if let Some(size) = optional_number(&input) {
+                    unit.size = size;
+                }
+                *size_input = input;
+            }
+            IngrediantEdit::UnitBase(index, base) => {
+                if let Some(unit) = ingrediant.units.get_mut(index) {
+                    unit.base = base;
+                }
+            }
+            IngrediantEdit::Nutrient(nutrient, input) => {
+                self.nutrient_inputs.insert(nutrient, input);
+                self.update_nutrition(ingrediant);
+            }
+            IngrediantEdit::NutritionPer(input) => {
+                self.nutrition_per_input = input;
+                self.update_nutrition(ingrediant);
+            }
+            IngrediantEdit::NutritionPerUnit(unit) => {
+                self.nutrition_per_unit = unit;
+                self.update_nutrition(ingrediant);
+            }
+            IngrediantEdit::RemoveUnit(_) => {}
+        }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let Some(ingrediant) = state.model.ingrediants.get(self.ingrediant_id) else {
+            return text("Ingrediant not found").into();
+        };
+        let ingrediant_id = self.ingrediant_id;
+
+        let field = |label, input: Element<'a, Message>| {
+            row![text(label).width(Length::FillPortion(2)), input]
+                .spacing(5)
+                .align_items(iced::Alignment::Center)
+        };
+
+        let name = text_input("Name", &ingrediant.name)
+            .on_input(|name| Message::EditIngrediant(IngrediantEdit::Name(name)));
+        let aisle = pick_list(
+            aisles::choices(&state.model),
+            Some(aisles::AisleChoice(ingrediant.category.clone())),
+            move |choice| Message::SetIngrediantAisle(ingrediant_id, choice.0),
+        );
+        let density = text_input("g per ml", &self.density_input)
+            .on_input(|input| Message::EditIngrediant(IngrediantEdit::Density(input)))
+            .width(Length::FillPortion(3));
+        let piece_weight = text_input("g each", &self.piece_weight_input)
+            .on_input(|input| Message::EditIngrediant(IngrediantEdit::PieceWeight(input)))
+            .width(Length::FillPortion(3));
+
+        let units = ingrediant
+            .units
+            .iter()
+            .zip(&self.unit_size_inputs)
+            .enumerate()
+            .map(|(index, (unit, size_input))| {
+                // An unsized unit can't be converted, so it can only go once nothing uses it
+                let uses = match unit.size {
+                    Some(_) => 0,
+                    None => unit_uses(&state.model, ingrediant_id, index),
+                };
+                let unit_row = row![
+                    delete_button().on_press_maybe(
+                        (uses == 0)
+                            .then_some(Message::EditIngrediant(IngrediantEdit::RemoveUnit(index)))
+                    ),
+                    text_input(
+                        "Name",
+                        self.unit_name_inputs
+                            .get(index)
+                            .map_or(&*unit.name, String::as_str)
+                    )
+                    .on_input(move |name| {
+                        Message::EditIngrediant(IngrediantEdit::UnitName(index, name))
+                    })
+                    .width(Length::FillPortion(3)),
+                    text_input("Size", size_input)
+                        .on_input(move |input| {
+                            Message::EditIngrediant(IngrediantEdit::UnitSize(index, input))
+                        })
+                        .width(Length::FillPortion(2)),
+                    pick_list(UNIT_BASES, Some(unit.base), move |base| {
+                        Message::EditIngrediant(IngrediantEdit::UnitBase(index, base))
+                    }),
+                ]
+                .spacing(5)
+                .align_items(iced::Alignment::Center);
+                let note = (uses > 0).then(|| {
+                    let ammounts = if uses == 1 { "amount" } else { "amounts" };
+                    text(format!(
+                        "Used by {uses} {ammounts}, give it a size to remove it"
+                    ))
+                    .size(12)
+                });
+                col![unit_row].push_maybe(note).spacing(2).into()
+            });
+        let unit_adder = row![
+            text_input("e.g. clove", &self.unit_input)
+                .on_input(|input| Message::EditIngrediant(IngrediantEdit::UnitInput(input)))
+                .on_submit(Message::EditIngrediant(IngrediantEdit::AddUnit)),
+            button("Add").on_press(Message::EditIngrediant(IngrediantEdit::AddUnit)),
+        ]
+        .spacing(10);
+
+        let food = row![
+            text(ingrediant.food.as_deref().unwrap_or("Not linked to a food")).width(Length::Fill),
+            button("Look up").on_press(Message::LookUpFood),
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+        let food = col![food]
+            .push_maybe(
+                state
+                    .food_table_error
+                    .as_deref()
+                    .map(|err| text(err).size(12)),
+            )
+            .spacing(2);
+        let per = row![
+            text_input("100", &self.nutrition_per_input)
+                .on_input(|input| Message::EditIngrediant(IngrediantEdit::NutritionPer(input)))
+                .width(Length::FillPortion(2)),
+            pick_list(
+                unit_choices(
+                    Some(ingrediant),
+                    state.preferences.unit_system,
+                    self.nutrition_per_unit
+                ),
+                Some(UnitChoice::new(Some(ingrediant), self.nutrition_per_unit)),
+                |choice| Message::EditIngrediant(IngrediantEdit::NutritionPerUnit(choice.unit)),
+            ),
+        ]
+        .spacing(5)
+        .width(Length::FillPortion(3));
+        let nutrients = Nutrient::ALL.into_iter().map(|nutrient| {
+            let input = self
+                .nutrient_inputs
+                .get(&nutrient)
+                .map_or("", String::as_str);
+            field(
+                nutrient.name(),
+                text_input(nutrient.unit(), input)
+                    .on_input(move |input| {
+                        Message::EditIngrediant(IngrediantEdit::Nutrient(nutrient, input))
+                    })
+                    .width(Length::FillPortion(3))
+                    .into(),
+            )
+            .into()
+        });
+
+        let content = scrollable(
+            col![
+                name,
+                field("Aisle", aisle.into()),
+                field("Density", density.into()),
+                field("Weight of one", piece_weight.into()),
+                text("Amounts in grams, millilitres and pieces are added up once these are known")
+                    .size(12),
+                text("Units").size(20),
+                col(units).spacing(5),
+                unit_adder,
+                text("Give a unit a size to add it up with amounts in grams or millilitres")
+                    .size(12),
+                text("Nutrition").size(20),
+                food,
+                field("Per", per.into()),
+                col(nutrients).spacing(10),
+            ]
+            .spacing(10),
+        );
+
+        iced_aw::Modal::new(
+            content,
+            self.food_picker.as_ref().map(|picker| picker.view()),
+        )
+        .backdrop(Message::ClosePicker)
+        .on_esc(Message::ClosePicker)
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::meal::Meal;
+
+    #[test]
+    fn keeps_last_valid_number() {
+        let mut model = Model::default();
+        let egg = model.ingrediants.push(Ingrediant::new("Egg".into()));
+        let mut page = IngrediantEditorPage::new(egg, &model);
+
+        page.edit(&mut model, IngrediantEdit::PieceWeight("60".to_owned()));
+        page.edit(&mut model, IngrediantEdit::PieceWeight("60.".to_owned()));
+        page.edit(&mut model, IngrediantEdit::PieceWeight("-".to_owned()));
+        assert_eq!(model.ingrediants.get(egg).unwrap().piece_weight, Some(60.0));
+        assert_eq!(page.piece_weight_input, "-");
+
+        page.edit(&mut model, IngrediantEdit::PieceWeight(String::new()));
+        assert_eq!(model.ingrediants.get(egg).unwrap().piece_weight, None);
+    }
+
+    #[test]
+    fn nutrition_follows_inputs() {
+        let mut model = Model::default();
+        let oats = model.ingrediants.push(Ingrediant::new("Oats".into()));
+        let mut page = IngrediantEditorPage::new(oats, &model);
+
+        page.edit(&mut model, IngrediantEdit::NutritionPer("40".to_owned()));
+        assert_eq!(model.ingrediants.get(oats).unwrap().nutrition, None);
+        page.edit(
+            &mut model,
+            IngrediantEdit::Nutrient(Nutrient::Energy, "150".to_owned()),
+        );
+        let nutrition = model.ingrediants.get(oats).unwrap().nutrition.clone();
+        let nutrition = nutrition.unwrap();
+        assert_eq!(nutrition.per.quantity, 40.0);
+        assert_eq!(nutrition.nutrients.energy, 150.0);
+
+        page.edit(
+            &mut model,
+            IngrediantEdit::Nutrient(Nutrient::Energy, String::new()),
+        );
+        assert_eq!(model.ingrediants.get(oats).unwrap().nutrition, None);
+    }
+
+    #[test]
+    fn removing_a_unit_converts_amounts() {
+        let mut model = Model::default();
+        let tomatoes = model.ingrediants.push(Ingrediant::new("Tomatoes".into()));
+        let mut page = IngrediantEditorPage::new(tomatoes, &model);
+        for unit in ["can", "punnet"] {
+            page.edit(&mut model, IngrediantEdit::UnitInput(unit.to_owned()));
+            page.edit(&mut model, IngrediantEdit::AddUnit);
+        }
+        page.edit(&mut model, IngrediantEdit::UnitSize(0, "400".to_owned()));
+        let stock = |quantity, unit| IngrediantQuantity { quantity, unit };
+        let mut meal = Meal::new("Sauce".into());
+        meal.ingrediants
+            .insert(tomatoes, stock(2.0, Unit::Custom(0)));
+        let sauce = model.meals.push(meal);
+        model.pantry.insert(tomatoes, stock(1.0, Unit::Custom(1)));
+
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+
+        assert_eq!(
+            model.meals.get(sauce).unwrap().ingrediants.get(&tomatoes),
+            Some(&stock(800.0, Unit::Solid(SolidUnit::Grams)))
+        );
+        assert_eq!(
+            model.pantry.get(&tomatoes),
+            Some(&stock(1.0, Unit::Custom(0)))
+        );
+        assert_eq!(page.unit_size_inputs, [""]);
+        assert_eq!(page.unit_name_inputs, ["punnet"]);
+    }
+
+    #[test]
+    fn keeps_unsized_units_in_use() {
+        let mut model = Model::default();
+        let tomatoes = model.ingrediants.push(Ingrediant::new("Tomatoes".into()));
+        let mut page = IngrediantEditorPage::new(tomatoes, &model);
+        page.edit(&mut model, IngrediantEdit::UnitInput("punnet".to_owned()));
+        page.edit(&mut model, IngrediantEdit::AddUnit);
+        let stock = |quantity, unit| IngrediantQuantity { quantity, unit };
+        model.pantry.insert(tomatoes, stock(2.0, Unit::Custom(0)));
+        assert_eq!(unit_uses(&model, tomatoes, 0), 1);
+
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(model.ingrediants.get(tomatoes).unwrap().units.len(), 1);
+        assert_eq!(page.unit_name_inputs, ["punnet"]);
+        assert_eq!(
+            model.pantry.get(&tomatoes),
+            Some(&stock(2.0, Unit::Custom(0)))
+        );
+
+        model.pantry.remove(&tomatoes);
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert!(model.ingrediants.get(tomatoes).unwrap().units.is_empty());
+    }
+
+    #[test]
+    fn removing_a_unit_moves_nutrition() {
+        let mut model = Model::default();
+        let tomatoes = model.ingrediants.push(Ingrediant::new("Tomatoes".into()));
+        let mut page = IngrediantEditorPage::new(tomatoes, &model);
+        for unit in ["can", "punnet", "crate"] {
+            page.edit(&mut model, IngrediantEdit::UnitInput(unit.to_owned()));
+            page.edit(&mut model, IngrediantEdit::AddUnit);
+        }
+        page.edit(&mut model, IngrediantEdit::UnitSize(0, "400".to_owned()));
+        page.edit(
+            &mut model,
+            IngrediantEdit::Nutrient(Nutrient::Energy, "80".to_owned()),
+        );
+        let per = |model: &Model| {
+            let ingrediant = model.ingrediants.get(tomatoes).unwrap();
+            ingrediant.nutrition.as_ref().unwrap().per.clone()
+        };
+        let stock = |quantity, unit| IngrediantQuantity { quantity, unit };
+
+        // A sized unit becomes its size
+        page.edit(&mut model, IngrediantEdit::NutritionPer("2".to_owned()));
+        page.edit(
+            &mut model,
+            IngrediantEdit::NutritionPerUnit(Unit::Custom(0)),
+        );
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(per(&model), stock(800.0, Unit::Solid(SolidUnit::Grams)));
+        assert_eq!(page.nutrition_per_input, "800");
+
+        // Later units move down
+        page.edit(
+            &mut model,
+            IngrediantEdit::NutritionPerUnit(Unit::Custom(1)),
+        );
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(per(&model), stock(800.0, Unit::Custom(0)));
+
+        // Without a size it goes back to 100 g
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(per(&model), Nutrition::default().per);
+        assert_eq!(page.nutrition_per_unit, Unit::Solid(SolidUnit::Grams));
+    }
+
+    #[test]
+    fn removing_a_unit_moves_unsaved_nutrition_unit() {
+        let mut model = Model::default();
+        let tomatoes = model.ingrediants.push(Ingrediant::new("Tomatoes".into()));
+        let mut page = IngrediantEditorPage::new(tomatoes, &model);
+        for unit in ["can", "punnet", "crate"] {
+            page.edit(&mut model, IngrediantEdit::UnitInput(unit.to_owned()));
+            page.edit(&mut model, IngrediantEdit::AddUnit);
+        }
+        page.edit(&mut model, IngrediantEdit::UnitSize(0, "400".to_owned()));
+        page.edit(&mut model, IngrediantEdit::NutritionPer("2".to_owned()));
+        page.edit(
+            &mut model,
+            IngrediantEdit::NutritionPerUnit(Unit::Custom(0)),
+        );
+        assert_eq!(model.ingrediants.get(tomatoes).unwrap().nutrition, None);
+
+        // A sized unit becomes its size
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(page.nutrition_per_unit, Unit::Solid(SolidUnit::Grams));
+        assert_eq!(page.nutrition_per_input, "800");
+
+        // Later units move down
+        page.edit(
+            &mut model,
+            IngrediantEdit::NutritionPerUnit(Unit::Custom(1)),
+        );
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(page.nutrition_per_unit, Unit::Custom(0));
+
+        // Without a size it goes back to 100 g
+        page.edit(&mut model, IngrediantEdit::RemoveUnit(0));
+        assert_eq!(page.nutrition_per_unit, Nutrition::default().per.unit);
+        assert_eq!(page.nutrition_per_input, "100");
+    }
+
+    #[test]
+    fn unit_names_stay_valid() {
+        let mut model = Model::default();
+        let garlic = model.ingrediants.push(Ingrediant::new("Garlic".into()));
+        let mut page = IngrediantEditorPage::new(garlic, &model);
+        for unit in ["clove", "bulb"] {
+            page.edit(&mut model, IngrediantEdit::UnitInput(unit.to_owned()));
+            page.edit(&mut model, IngrediantEdit::AddUnit);
+        }
+        let names = |model: &Model| {
+            model
+                .ingrediants
+                .get(garlic)
+                .unwrap()
+                .units
+                .iter()
+                .map(|unit| unit.name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        for input in ["", "Clove", "g", "head "] {
+            page.edit(&mut model, IngrediantEdit::UnitName(1, input.to_owned()));
+        }
+        assert_eq!(names(&model), ["clove", "head"]);
+        page.edit(&mut model, IngrediantEdit::UnitName(1, "g".to_owned()));
+        assert_eq!(names(&model), ["clove", "head"]);
+        assert_eq!(page.unit_name_inputs[1], "g");
+    }
+}
@@ -0,0 +1,394 @@
+//! Reads ingrediant lines as they are written in recipes, e.g. "2 tbsp olive oil", "1.5kg potatoes"
+//! or "a pinch of salt".
+
+use crate::unit::{AmbiguosUnit, LiquidUnit, SolidUnit, Unit};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedLine {
+    pub name: String,
+    pub quantity: f64,
+    pub unit: Unit,
+}
+
+/// Words for each unit, matched ignoring case
+static UNIT_WORDS: &[(&str, Unit)] = &[
+    ("g", Unit::Solid(SolidUnit::Grams)),
+    ("gr", Unit::Solid(SolidUnit::Grams)),
+    ("gram", Unit::Solid(SolidUnit::Grams)),
+    ("grams", Unit::Solid(SolidUnit::Grams)),
+    ("gramme", Unit::Solid(SolidUnit::Grams)),
+    ("grammes", Unit::Solid(SolidUnit::Grams)),
+    ("kg", Unit::Solid(SolidUnit::KiloGrams)),
+    ("kgs", Unit::Solid(SolidUnit::KiloGrams)),
+    ("kilo", Unit::Solid(SolidUnit::KiloGrams)),
+    ("kilos", Unit::Solid(SolidUnit::KiloGrams)),
+    ("kilogram", Unit::Solid(SolidUnit::KiloGrams)),
+    ("kilograms", Unit::Solid(SolidUnit::KiloGrams)),
+    ("pinch", Unit::Solid(SolidUnit::Pinch)),
+    ("pinches", Unit::Solid(SolidUnit::Pinch)),
+    ("ml", Unit::Liquid(LiquidUnit::MilliLiters)),
+    ("millilitre", Unit::Liquid(LiquidUnit::MilliLiters)),
+    ("millilitres", Unit::Liquid(LiquidUnit::MilliLiters)),
+    ("milliliter", Unit::Liquid(LiquidUnit::MilliLiters)),
+    ("milliliters", Unit::Liquid(LiquidUnit::MilliLiters)),
+    ("l", Unit::Liquid(LiquidUnit::Liters)),
+    ("litre", Unit::Liquid(LiquidUnit::Liters)),
+    ("litres", Unit::Liquid(LiquidUnit::Liters)),
+    ("liter", Unit::Liquid(LiquidUnit::Liters)),
+    ("liters", Unit::Liquid(LiquidUnit::Liters)),
+    ("tsp", Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+    ("tsps", Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+    ("teaspoon", Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+    ("teaspoons", Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+    ("tbsp", Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+    ("tbsps", Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+    ("tbs", Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+    ("tablespoon", Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+    ("tablespoons", Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+    ("x", Unit::Ambigous(AmbiguosUnit::Count)),
+    ("oz", Unit::Solid(SolidUnit::Ounces)),
+    ("ounce", Unit::Solid(SolidUnit::Ounces)),
+    ("ounces", Unit::Solid(SolidUnit::Ounces)),
+    ("lb", Unit::Solid(SolidUnit::Pounds)),
+    ("lbs", Unit::Solid(SolidUnit::Pounds)),
+    ("pound", Unit::Solid(SolidUnit::Pounds)),
+    ("pounds", Unit::Solid(SolidUnit::Pounds)),
+    ("floz", Unit::Liquid(LiquidUnit::FluidOunces)),
+    ("cup", Unit::Liquid(LiquidUnit::Cups)),
+    ("cups", Unit::Liquid(LiquidUnit::Cups)),
+    ("pt", Unit::Liquid(LiquidUnit::Pints)),
+    ("pint", Unit::Liquid(LiquidUnit::Pints)),
+    ("pints", Unit::Liquid(LiquidUnit::Pints)),
+    ("qt", Unit::Liquid(LiquidUnit::Quarts)),
+    ("quart", Unit::Liquid(LiquidUnit::Quarts)),
+    ("quarts", Unit::Liquid(LiquidUnit::Quarts)),
+];
+
+/// Words that turn an ounce into a fluid ounce when they come before it
+const FLUID: &[&str] = &["fl", "fl.", "fluid"];
+
+/// Quantities written out as words
+static NUMBER_WORDS: &[(&str, f64)] = &[
+    ("a", 1.0),
+    ("an", 1.0),
+    ("one", 1.0),
+    ("two", 2.0),
+    ("three", 3.0),
+    ("four", 4.0),
+    ("half", 0.5),
+    ("dozen", 12.0),
+];
+
+fn number_word(word: &str) -> Option<f64> {
+    let word = word.to_lowercase();
+    NUMBER_WORDS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, value)| *value)
+}
+
+fn unicode_fraction(c: char) -> Option<f64> {
+    Some(match c {
+        '¼' => 0.25,
+        '½' => 0.5,
+        '¾' => 0.75,
+        '⅓' => 1.0 / 3.0,
+        '⅔' => 2.0 / 3.0,
+        '⅛' => 0.125,
+        _ => return None,
+    })
+}
+
+fn unit_word(word: &str) -> Option<Unit> {
+    let word = word.trim_end_matches('.').to_lowercase();
+    UNIT_WORDS
+        .iter()
+        .find(|(name, _)| *name == word)
+        .map(|(_, unit)| *unit)
+}
+
+/// Reads a number from the start of `word`, returning it and whatever follows, so "1.5kg" gives
+/// 1.5 and "kg". Handles decimals, "1/2" style and unicode fractions. A comma followed by one or two
+/// digits is a decimal comma, followed by three it separates thousands.
+fn leading_number(word: &str) -> Option<(f64, &str)> {
+    let mut number = String::new();
+    let mut end = 0;
+    while let Some(c) = word[end..].chars().next() {
+        if c.is_ascii_digit() || c == '.' || c == '/' {
+            number.push(c);
+            end += 1;
+            continue;
+        }
+        if c != ',' || number.is_empty() {
+            break;
+        }
+        let digits = word[end + 1..]
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(word.len() - end - 1);
+        match digits {
+            1 | 2 => number.push('.'),
+            3 => {}
+            _ => break,
+        }
+        end += 1;
+    }
+    let rest = &word[end..];
+
+    let mut value = if number.is_empty() {
+        None
+    } else if let Some((numerator, denominator)) = number.split_once('/') {
+        let numerator: f64 = numerator.parse().ok()?;
+        let denominator: f64 = denominator.parse().ok()?;
+        (denominator != 0.0).then(|| numerator / denominator)
+    } else {
+        Some(number.parse().ok()?)
+    };
+
+    let mut rest = rest;
+    if let Some(fraction) = rest.chars().next().and_then(unicode_fraction) {
+        value = Some(value.unwrap_or(0.0) + fraction);
+        rest = &rest[rest.chars().next().map_or(0, char::len_utf8)..];
+    }
+    value.map(|value| (value, rest))
+}
+
+/// Whether `line` has a quantity or unit before the name, like "2 eggs", "a pinch of salt" or "cup
+/// of milk". A name that merely starts with one, like "Pound cake" or "Half and half", doesn't.
+pub fn has_quantity(line: &str) -> bool {
+    let mut words = line.split_whitespace();
+    let Some(first) = words.next() else {
+        return false;
+    };
+    let second = words.next();
+    if leading_number(first).is_some() {
+        true
+    } else if number_word(first).is_some() {
+        second.is_some_and(|word| {
+            unit_word(word).is_some() || FLUID.contains(&word.to_lowercase().as_str())
+        })
+    } else {
+        unit_word(first).is_some() && second.is_some_and(|word| word.eq_ignore_ascii_case("of"))
+    }
+}
+
+/// Drops preparation notes after a comma or in brackets, so "onion (red), diced" gives "onion"
+fn without_notes(name: &str) -> String {
+    let mut kept = String::new();
+    let mut depth = 0_usize;
+    for c in name.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => break,
+            c if depth == 0 => kept.push(c),
+            _ => {}
+        }
+    }
+    kept.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse(line: &str) -> ParsedLine {
+    let mut words = line.split_whitespace().peekable();
+
+    let mut quantity = None;
+    let mut unit = None;
+    while let Some(word) = words.peek() {
+        if let Some((number, rest)) = leading_number(word) {
+            // "1 1/2" adds up, "2-3" takes the lower end
+            let rest = rest.split('-').next().unwrap_or_default();
+            quantity = Some(quantity.unwrap_or(0.0) + number);
+            words.next();
+            if !rest.is_empty() {
+                unit = unit_word(rest);
+                break;
+            }
+        } else if let Some(number) = number_word(word).filter(|_| quantity.is_none()) {
+            quantity = Some(number);
+            words.next();
+        } else {
+            break;
+        }
+    }
+
+    if unit.is_none() {
+        let mut ahead = words.clone();
+        let fluid = ahead
+            .next()
+            .is_some_and(|word| FLUID.contains(&word.to_lowercase().as_str()));
+        if fluid
+            && ahead.peek().and_then(|word| unit_word(word)) == Some(Unit::Solid(SolidUnit::Ounces))
+        {
+            unit = Some(Unit::Liquid(LiquidUnit::FluidOunces));
+            words.nth(1);
+        } else {
+            unit = words.peek().and_then(|word| unit_word(word));
+            if unit.is_some() {
+                words.next();
+            }
+        }
+    }
+    if words
+        .peek()
+        .is_some_and(|word| word.eq_ignore_ascii_case("of"))
+    {
+        words.next();
+    }
+
+    // The quantity is read first as it may have a decimal comma
+    let name = without_notes(&words.collect::<Vec<_>>().join(" "));
+    match quantity {
+        Some(quantity) => ParsedLine {
+            name,
+            quantity,
+            unit: unit.unwrap_or(Unit::Ambigous(AmbiguosUnit::Count)),
+        },
+        // "pinch of salt" is one pinch
+        None if unit.is_some() => ParsedLine {
+            name,
+            quantity: 1.0,
+            unit: unit.unwrap_or_default(),
+        },
+        None => ParsedLine {
+            name,
+            quantity: 0.0,
+            unit: Unit::default(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(name: &str, quantity: f64, unit: Unit) -> ParsedLine {
+        ParsedLine {
+            name: name.to_owned(),
+            quantity,
+            unit,
+        }
+    }
+
+    #[test]
+    fn quantity_unit_name() {
+        assert_eq!(
+            parse("2 tbsp olive oil"),
+            line("olive oil", 2.0, Unit::Ambigous(AmbiguosUnit::TableSpoon))
+        );
+        assert_eq!(
+            parse("500 grams plain flour"),
+            line("plain flour", 500.0, Unit::Solid(SolidUnit::Grams))
+        );
+        assert_eq!(
+            parse("1 L Milk"),
+            line("Milk", 1.0, Unit::Liquid(LiquidUnit::Liters))
+        );
+    }
+
+    #[test]
+    fn unit_attached_to_number() {
+        assert_eq!(
+            parse("1.5kg potatoes"),
+            line("potatoes", 1.5, Unit::Solid(SolidUnit::KiloGrams))
+        );
+        assert_eq!(
+            parse("250ml stock"),
+            line("stock", 250.0, Unit::Liquid(LiquidUnit::MilliLiters))
+        );
+    }
+
+    #[test]
+    fn words_for_one() {
+        assert_eq!(
+            parse("a pinch of salt"),
+            line("salt", 1.0, Unit::Solid(SolidUnit::Pinch))
+        );
+        assert_eq!(
+            parse("Pinch of nutmeg"),
+            line("nutmeg", 1.0, Unit::Solid(SolidUnit::Pinch))
+        );
+        assert_eq!(
+            parse("two eggs"),
+            line("eggs", 2.0, Unit::Ambigous(AmbiguosUnit::Count))
+        );
+        assert_eq!(
+            parse("an onion"),
+            line("onion", 1.0, Unit::Ambigous(AmbiguosUnit::Count))
+        );
+    }
+
+    #[test]
+    fn fractions() {
+        assert_eq!(
+            parse("1 1/2 tsp cumin"),
+            line("cumin", 1.5, Unit::Ambigous(AmbiguosUnit::TeaSpoon))
+        );
+        assert_eq!(
+            parse("½ tsp chilli flakes"),
+            line("chilli flakes", 0.5, Unit::Ambigous(AmbiguosUnit::TeaSpoon))
+        );
+        assert_eq!(
+            parse("2-3 carrots, peeled"),
+            line("carrots", 2.0, Unit::Ambigous(AmbiguosUnit::Count))
+        );
+    }
+
+    #[test]
+    fn decimal_comma() {
+        assert_eq!(
+            parse("1,5 kg flour"),
+            line("flour", 1.5, Unit::Solid(SolidUnit::KiloGrams))
+        );
+        assert_eq!(
+            parse("0,5l milk (whole), warmed"),
+            line("milk", 0.5, Unit::Liquid(LiquidUnit::Liters))
+        );
+    }
+
+    #[test]
+    fn thousands_separator() {
+        assert_eq!(
+            parse("1,000 g flour"),
+            line("flour", 1000.0, Unit::Solid(SolidUnit::Grams))
+        );
+        assert_eq!(
+            parse("1,250ml water"),
+            line("water", 1250.0, Unit::Liquid(LiquidUnit::MilliLiters))
+        );
+        assert_eq!(
+            parse("3, large eggs"),
+            line("large eggs", 3.0, Unit::Ambigous(AmbiguosUnit::Count))
+        );
+    }
+
+    #[test]
+    fn us_units() {
+        assert_eq!(
+            parse("2 cups flour"),
+            line("flour", 2.0, Unit::Liquid(LiquidUnit::Cups))
+        );
+        assert_eq!(
+            parse("8 fl oz milk"),
+            line("milk", 8.0, Unit::Liquid(LiquidUnit::FluidOunces))
+        );
+        assert_eq!(
+            parse("1lb ground beef"),
+            line("ground beef", 1.0, Unit::Solid(SolidUnit::Pounds))
+        );
+    }
+
+    #[test]
+    fn no_quantity() {
+        assert_eq!(
+            parse("Salt and pepper (to taste)"),
+            line("Salt and pepper", 0.0, Unit::default())
+        );
+        assert!(has_quantity("½ tsp salt"));
+        assert!(has_quantity("200g flour"));
+        assert!(has_quantity("a pinch of salt"));
+        assert!(has_quantity("half cup milk"));
+        assert!(has_quantity("Pinch of nutmeg"));
+        assert!(!has_quantity("Pound cake"));
+        assert!(!has_quantity("Half and half"));
+        assert!(!has_quantity("A salad"));
+    }
+}
@@ -0,0 +1,145 @@
+//! Layout of save files written before the versioned format, when days were numbered rather than
+//! dated. Only used to read old files, these types mirror what bincode wrote back then.
+
+use std::{collections::BTreeMap, ops::Range, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::{
+    date, generational_map::GenerationalMap, ingrediant::IngrediantKey, meal::MealKey, unit::Unit,
+    Date,
+};
+
+type LegacyDate = usize;
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+pub struct State {
+    page: Page,
+    stack: Vec<Page>,
+    days: BTreeMap<LegacyDate, LegacyDay>,
+    meals: GenerationalMap<Meal>,
+    ingrediants: GenerationalMap<Ingrediant>,
+    meal_creation_input_field: String,
+    save: SaveState,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+enum Page {
+    DayView(DayPage),
+    MealList,
+    MealEditorView(MealEditorPage),
+    ShoppingView { from: LegacyDate, until: LegacyDate },
+    WeekView(Range<LegacyDate>),
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct DayPage {
+    date: LegacyDate,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct MealEditorPage {
+    meal_id: MealKey,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct SaveState {
+    saved: bool,
+    saving: bool,
+}
+
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct LegacyDay {
+    date: LegacyDate,
+    meals: Vec<MealKey>,
+}
+
+#[derive(Deserialize)]
+struct Meal {
+    name: Arc<str>,
+    ingrediants: BTreeMap<IngrediantKey, IngrediantQuantity>,
+}
+
+#[derive(Deserialize)]
+struct Ingrediant {
+    name: Arc<str>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IngrediantQuantity {
+    quantity: f64,
+    unit: Unit,
+}
+
+impl State {
+    /// Converts to the layout of save file version 1, day 1 becomes `start` and the days after it
+    /// follow on. Navigation is not kept as the pages refer to the old day numbers.
+    pub fn migrate(self, start: Date) -> Value {
+        let days: Map<String, Value> = self
+            .days
+            .into_iter()
+            .map(|(number, day)| {
+                let date = date::add_days(start, number.saturating_sub(1) as u64);
+                let meals: Vec<Value> = day
+                    .meals
+                    .into_iter()
+                    .map(|meal_id| json!({ "meal_id": meal_id, "portions": 1 }))
+                    .collect();
+                (date.to_string(), json!({ "date": date, "meals": meals }))
+            })
+            .collect();
+
+        json!({
+            "days": days,
+            "meals": self.meals.map(|meal| json!({
+                "name": meal.name,
+                "servings": 1,
+                "ingrediants": meal.ingrediants.into_iter().collect::<Vec<_>>(),
+            })),
+            "ingrediants": self.ingrediants.map(|ingrediant| json!({ "name": ingrediant.name })),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_days_with_their_number() {
+        // Bincode writes structs as tuples of their fields and enums as their variant index
+        // followed by the fields
+        let key = (0u64, 0u64);
+        let page = (4u32, (1u64, 100u64));
+        let stack: Vec<()> = vec![];
+        let days = BTreeMap::from([(3u64, (3u64, vec![key, key]))]);
+        let no_entries = (Vec::<()>::new(), Vec::<u64>::new());
+        let bytes = bincode::serialize(&(
+            page,
+            stack,
+            days,
+            &no_entries,
+            &no_entries,
+            "",
+            (true, false),
+        ))
+        .unwrap();
+
+        let legacy: State = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(legacy.days[&3].date, 3);
+
+        let start = date::week_start(date::today());
+        let state = legacy.migrate(start);
+        let date = date::add_days(start, 2);
+        let day = &state["days"][date.to_string()];
+        assert_eq!(day["date"], json!(date));
+        assert_eq!(day["meals"].as_array().map(Vec::len), Some(2));
+    }
+}
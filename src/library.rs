@@ -0,0 +1,566 @@
+//! Human readable export of the meal library.
+//!
+//! Meals, ingrediants and days refer to each other by name rather than by key, so a library can be
+//! imported into a different save and merged with what is already there. Meals and ingrediants are
+//! matched by their `StableId` when they have one, so renames carry across, and by name otherwise.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ingrediant::{CustomUnit, Ingrediant, IngrediantKey, IngrediantQuantity},
+    meal::{Meal, MealKey, PlannedMeal},
+    nutrition::{Nutrients, Nutrition},
+    stable_id::StableId,
+    unit::{Unit, UNITS},
+    Date, Day, Model,
+};
+
+const FORMAT: &str = "graze-library";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Library {
+    format: String,
+    version: u32,
+    ingrediants: Vec<IngrediantEntry>,
+    meals: Vec<MealEntry>,
+    days: Vec<DayEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct IngrediantEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<StableId>,
+    name: Arc<str>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    category: Option<Arc<str>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    density: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    piece_weight: Option<f64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    units: Vec<CustomUnit>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nutrition: Option<NutritionEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    food: Option<Arc<str>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct NutritionEntry {
+    per: AmountEntry,
+    nutrients: Nutrients,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct AmountEntry {
+    quantity: f64,
+    /// Unit abbreviation, or the name of one of the ingrediant's own units, as shown in the app
+    unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct MealEntry {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<StableId>,
+    name: Arc<str>,
+    #[serde(default = "one")]
+    servings: u32,
+    #[serde(default)]
+    ingrediants: Vec<QuantityEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct QuantityEntry {
+    name: Arc<str>,
+    quantity: f64,
+    /// Unit abbreviation, or the name of one of the ingrediant's own units, as shown in the app
+    unit: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct DayEntry {
+    date: Date,
+    meals: Vec<PlannedEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PlannedEntry {
+    meal: Arc<str>,
+    #[serde(default = "one")]
+    portions: u32,
+}
+
+const fn one() -> u32 {
+    1
+}
+
+fn parse_unit(abreviation: &str) -> Result<Unit, String> {
+    UNITS
+        .iter()
+        .find(|unit| unit.abreviation() == abreviation)
+        .copied()
+        .ok_or_else(|| format!("unknown unit \"{abreviation}\""))
+}
+
+impl Library {
+    pub fn export(model: &Model) -> Self {
+        let meal_name = |key| model.meals.get(key).map(|meal| meal.name.clone());
+
+        let ingrediants = model
+            .ingrediants
+            .values()
+            .map(|ingrediant| IngrediantEntry {
+                id: Some(ingrediant.id),
+                name: ingrediant.name.clone(),
+                category: ingrediant.category.clone(),
+                density: ingrediant.density,
+                piece_weight: ingrediant.piece_weight,
+                units: ingrediant.units.clone(),
+                nutrition: ingrediant
+                    .nutrition
+                    .as_ref()
+                    .map(|nutrition| NutritionEntry {
+                        per: AmountEntry {
+                            quantity: nutrition.per.quantity,
+                            unit: ingrediant.unit_label(nutrition.per.unit).to_owned(),
+                        },
+                        nutrients: nutrition.nutrients,
+                    }),
+                food: ingrediant.food.clone(),
+            })
+            .collect();
+
+        let meals = model
+            .meals
+            .values()
+            .map(|meal| MealEntry {
+                id: Some(meal.id),
+                name: meal.name.clone(),
+                servings: meal.servings,
+                ingrediants: meal
+                    .ingrediants
+                    .iter()
+                    .filter_map(|(key, quantity)| {
+                        let ingrediant = model.ingrediants.get(*key)?;
+                        Some(QuantityEntry {
+                            name: ingrediant.name.clone(),
+                            quantity: quantity.quantity,
+                            unit: ingrediant.unit_label(quantity.unit).to_owned(),
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let days = model
+            .days
+            .values()
+            .map(|day| DayEntry {
+                date: day.date,
+                meals: day
+                    .meals
+                    .iter()
+                    .filter_map(|planned| {
+                        Some(PlannedEntry {
+                            meal: meal_name(planned.meal_id)?,
+                            portions: planned.portions,
+                        })
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Self {
+            format: FORMAT.to_owned(),
+            version: VERSION,
+            ingrediants,
+            meals,
+            days,
+        }
+    }
+
+    /// Adds the library to `model`. Meals and ingrediants are matched by id or else by name, a meal
+    /// that already exists takes the imported name, servings and quantities. Planned meals are added
+    /// to their day unless that meal is already planned then.
+    pub fn merge_into(self, model: &mut Model) -> Result<(), String> {
+        if self.format != FORMAT {
+            return Err(format!("not a Graze library (format \"{}\")", self.format));
+        }
+        if self.version > VERSION {
+            return Err(format!(
+                "library version {} is newer than this version of Graze",
+                self.version
+            ));
+        }
+
+        // Check every unit before changing anything so a bad file doesn't half import. Units the
+        // ingrediant defines itself are `None` until the ingrediant has been merged.
+        let custom_units: HashMap<&str, &[CustomUnit]> = self
+            .ingrediants
+            .iter()
+            .map(|entry| (&*entry.name, &*entry.units))
+            .collect();
+        let check_unit = |name: &str, unit: &str| {
+            parse_unit(unit).map(Some).or_else(|err| {
+                custom_units
+                    .get(name)
+                    .is_some_and(|units| units.iter().any(|custom| *custom.name == *unit))
+                    .then_some(None)
+                    .ok_or(err)
+            })
+        };
+        for entry in &self.ingrediants {
+            if let Some(nutrition) = &entry.nutrition {
+                check_unit(&entry.name, &nutrition.per.unit)?;
+            }
+        }
+        let units = self
+            .meals
+            .iter()
+            .flat_map(|meal| &meal.ingrediants)
+            .map(|entry| check_unit(&entry.name, &entry.unit))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut units = units.into_iter();
+
+        let mut ingrediant_keys = HashMap::new();
+        for entry in &self.ingrediants {
+            let key = ingrediant_key(model, entry.id, &entry.name);
+            ingrediant_keys.insert(entry.name.clone(), key);
+            if let Some(category) = &entry.category {
+                if !model.aisles.contains(category) {
+                    model.aisles.push(category.clone());
+                }
+                if let Some(ingrediant) = model.ingrediants.get_mut(key) {
+                    ingrediant.category = Some(category.clone());
+                }
+            }
+            if let Some(ingrediant) = model.ingrediants.get_mut(key) {
+                ingrediant.density = entry.density.or(ingrediant.density);
+                ingrediant.piece_weight = entry.piece_weight.or(ingrediant.piece_weight);
+                for unit in &entry.units {
+                    merge_unit(ingrediant, unit);
+                }
+                // Its own units are only numbered once they have been merged
+                if let Some(nutrition) = &entry.nutrition {
+                    let unit = parse_unit(&nutrition.per.unit)
+                        .ok()
+                        .or_else(|| ingrediant.custom_unit(&nutrition.per.unit))
+                        .unwrap_or_default();
+                    ingrediant.nutrition = Some(Nutrition {
+                        per: IngrediantQuantity {
+                            quantity: nutrition.per.quantity,
+                            unit,
+                        },
+                        nutrients: nutrition.nutrients,
+                    });
+                    ingrediant.food.clone_from(&entry.food);
+                }
+            }
+        }
+
+        let mut meal_keys = HashMap::new();
+        for entry in self.meals {
+            let ingrediants: BTreeMap<IngrediantKey, IngrediantQuantity> = entry
+                .ingrediants
+                .into_iter()
+                .zip(&mut units)
+                .map(|(quantity, unit)| {
+                    let key = ingrediant_keys
+                        .get(&quantity.name)
+                        .copied()
+                        .unwrap_or_else(|| ingrediant_key(model, None, &quantity.name));
+                    let unit = unit
+                        .or_else(|| model.ingrediants.get(key)?.custom_unit(&quantity.unit))
+                        .unwrap_or_default();
+                    (
+                        key,
+                        IngrediantQuantity {
+                            quantity: quantity.quantity,
+                            unit,
+                        },
+                    )
+                })
+                .collect();
+            let key = meal_key(model, entry.id, &entry.name);
+            meal_keys.insert(entry.name, key);
+            if let Some(meal) = model.meals.get_mut(key) {
+                meal.servings = entry.servings;
+                meal.ingrediants.extend(ingrediants);
+            }
+        }
+
+        for entry in self.days {
+            let planned: Vec<PlannedMeal> = entry
+                .meals
+                .into_iter()
+                .map(|planned| PlannedMeal {
+                    meal_id: meal_keys
+                        .get(&planned.meal)
+                        .copied()
+                        .unwrap_or_else(|| meal_key(model, None, &planned.meal)),
+                    portions: planned.portions,
+                })
+                .collect();
+            let day = model.days.entry(entry.date).or_insert_with(|| Day {
+                date: entry.date,
+                meals: Vec::new(),
+            });
+            for planned in planned {
+                if !day.meals.iter().any(|p| p.meal_id == planned.meal_id) {
+                    day.meals.push(planned);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the ingrediant with `id`, or else called `name`, adding it if there isn't one
+fn ingrediant_key(model: &mut Model, id: Option<StableId>, name: &Arc<str>) -> IngrediantKey {
+    let by_id = id.and_then(|id| {
+        model
+            .ingrediants
+            .iter()
+            .find(|(_, ingrediant)| ingrediant.id == id)
+            .map(|(key, _)| key)
+    });
+    if let Some(key) = by_id {
+        if let Some(ingrediant) = model.ingrediants.get_mut(key) {
+            ingrediant.name = name.clone();
+        }
+        return key;
+    }
+
+    let by_name = model
+        .ingrediants
+        .iter()
+        .find(|(_, ingrediant)| ingrediant.name == *name)
+        .map(|(key, _)| key);
+    by_name.unwrap_or_else(|| {
+        let mut ingrediant = Ingrediant::new(name.clone());
+        ingrediant.id = id.unwrap_or(ingrediant.id);
+        model.ingrediants.push(ingrediant)
+    })
+}
+
+/// Adds one of an imported ingrediant's own units, or takes its size if it already has it
+fn merge_unit(ingrediant: &mut Ingrediant, unit: &CustomUnit) {
+    match ingrediant.custom_unit(&unit.name) {
+        Some(Unit::Custom(index)) => {
+            if let Some(existing) = ingrediant.units.get_mut(usize::from(index)) {
+                if unit.size.is_some() {
+                    existing.size = unit.size;
+                    existing.base = unit.base;
+                }
+            }
+        }
+        _ => {
+            if ingrediant.add_unit(&unit.name).is_some() {
+                if let Some(added) = ingrediant.units.last_mut() {
+                    added.size = unit.size;
+                    added.base = unit.base;
+                }
+            }
+        }
+    }
+}
+
+/// Finds the meal with `id`, or else called `name`, adding it if there isn't one
+fn meal_key(model: &mut Model, id: Option<StableId>, name: &Arc<str>) -> MealKey {
+    let by_id = id.and_then(|id| {
+        model
+            .meals
+            .iter()
+            .find(|(_, meal)| meal.id == id)
+            .map(|(key, _)| key)
+    });
+    if let Some(key) = by_id {
+        if let Some(meal) = model.meals.get_mut(key) {
+            meal.name = name.clone();
+        }
+        return key;
+    }
+
+    let by_name = model
+        .meals
+        .iter()
+        .find(|(_, meal)| meal.name == *name)
+        .map(|(key, _)| key);
+    by_name.unwrap_or_else(|| {
+        let mut meal = Meal::new(name.clone());
+        meal.id = id.unwrap_or(meal.id);
+        model.meals.push(meal)
+    })
+}
+
+pub async fn write(path: String, library: Library) -> Result<(), String> {
+    let json = serde_json::to_vec_pretty(&library).map_err(|err| err.to_string())?;
+    async_std::fs::write(&path, json)
+        .await
+        .map_err(|err| format!("couldn't write {path}: {err}"))
+}
+
+pub async fn read(path: String) -> Result<Library, String> {
+    let json = async_std::fs::read(&path)
+        .await
+        .map_err(|err| format!("couldn't read {path}: {err}"))?;
+    serde_json::from_slice(&json).map_err(|err| format!("couldn't read {path}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unit::{AmbiguosUnit, SolidUnit};
+
+    fn model_with_bread() -> Model {
+        let mut model = Model::default();
+        let flour = model.ingrediants.push(Ingrediant::new("Flour".into()));
+        let mut bread = Meal::new("Bread".into());
+        bread.servings = 4;
+        bread.ingrediants.insert(
+            flour,
+            IngrediantQuantity {
+                quantity: 500.0,
+                unit: Unit::Solid(SolidUnit::Grams),
+            },
+        );
+        let bread = model.meals.push(bread);
+        let date = Date::from_ymd_opt(2024, 10, 14).unwrap();
+        model.days.insert(
+            date,
+            Day {
+                date,
+                meals: vec![PlannedMeal {
+                    meal_id: bread,
+                    portions: 2,
+                }],
+            },
+        );
+        model
+    }
+
+    #[test]
+    fn round_trip() {
+        let model = model_with_bread();
+        let json = serde_json::to_string(&Library::export(&model)).unwrap();
+        let mut imported = Model::default();
+        serde_json::from_str::<Library>(&json)
+            .unwrap()
+            .merge_into(&mut imported)
+            .unwrap();
+        assert_eq!(imported.meals, model.meals);
+        assert_eq!(imported.ingrediants, model.ingrediants);
+        assert_eq!(imported.days, model.days);
+    }
+
+    #[test]
+    fn merges_by_name() {
+        let library = Library::export(&model_with_bread());
+
+        let mut model = Model::default();
+        let egg = model.ingrediants.push(Ingrediant::new("Egg".into()));
+        let flour = model.ingrediants.push(Ingrediant::new("Flour".into()));
+        let mut bread = Meal::new("Bread".into());
+        bread.ingrediants.insert(
+            egg,
+            IngrediantQuantity {
+                quantity: 1.0,
+                unit: Unit::Ambigous(AmbiguosUnit::Count),
+            },
+        );
+        let bread = model.meals.push(bread);
+
+        library.clone().merge_into(&mut model).unwrap();
+        // Importing twice doesn't plan the meal twice
+        library.merge_into(&mut model).unwrap();
+
+        assert_eq!(model.ingrediants.len(), 2);
+        assert_eq!(model.meals.len(), 1);
+        let bread = model.meals.get(bread).unwrap();
+        assert_eq!(bread.servings, 4);
+        assert_eq!(
+            bread.ingrediants.keys().copied().collect::<Vec<_>>(),
+            vec![egg, flour]
+        );
+        let planned: Vec<_> = model.days.values().flat_map(|day| &day.meals).collect();
+        assert_eq!(planned.len(), 1);
+    }
+
+    #[test]
+    fn carries_custom_units() {
+        let mut model = model_with_bread();
+        let (_, flour) = model.ingrediants.iter_mut().next().unwrap();
+        let scoop = flour.add_unit("scoop").unwrap();
+        flour.units[0].size = Some(120.0);
+        let bread = model.meals.values_mut().next().unwrap();
+        bread.ingrediants.values_mut().next().unwrap().unit = scoop;
+
+        let library = Library::export(&model);
+        assert_eq!(library.meals[0].ingrediants[0].unit, "scoop");
+        let mut imported = Model::default();
+        library.merge_into(&mut imported).unwrap();
+        assert_eq!(imported.meals, model.meals);
+        assert_eq!(imported.ingrediants, model.ingrediants);
+    }
+
+    #[test]
+    fn carries_nutrition_per_custom_unit() {
+        let mut model = model_with_bread();
+        let (_, flour) = model.ingrediants.iter_mut().next().unwrap();
+        let scoop = flour.add_unit("scoop").unwrap();
+        let mut nutrition = Nutrition::default();
+        nutrition.per = IngrediantQuantity {
+            quantity: 1.0,
+            unit: scoop,
+        };
+        nutrition.nutrients.energy = 430.0;
+        flour.nutrition = Some(nutrition.clone());
+
+        let library = Library::export(&model);
+        assert_eq!(
+            library.ingrediants[0].nutrition.as_ref().unwrap().per.unit,
+            "scoop"
+        );
+
+        // The same units in another order
+        let mut merged = Model::default();
+        let flour = merged.ingrediants.push(Ingrediant::new("Flour".into()));
+        let existing = merged.ingrediants.get_mut(flour).unwrap();
+        existing.add_unit("bag").unwrap();
+        existing.add_unit("Scoop").unwrap();
+        library.merge_into(&mut merged).unwrap();
+
+        let flour = merged.ingrediants.get(flour).unwrap();
+        nutrition.per.unit = Unit::Custom(1);
+        assert_eq!(flour.nutrition, Some(nutrition));
+    }
+
+    #[test]
+    fn rejects_unknown_units() {
+        let mut library = Library::export(&model_with_bread());
+        library.meals[0].ingrediants[0].unit = "furlong".to_owned();
+        let mut model = Model::default();
+        assert!(library.merge_into(&mut model).is_err());
+        assert_eq!(model.meals.len(), 0);
+    }
+
+    #[test]
+    fn matches_renamed_by_id() {
+        let model = model_with_bread();
+        let mut library = Library::export(&model);
+        library.meals[0].name = "Sourdough".into();
+        library.days[0].meals[0].meal = "Sourdough".into();
+
+        let mut merged = model.clone();
+        library.merge_into(&mut merged).unwrap();
+        assert_eq!(merged.meals.len(), 1);
+        assert_eq!(&*merged.meals.values().next().unwrap().name, "Sourdough");
+        assert_eq!(merged.days, model.days);
+    }
+}
@@ -1,28 +1,49 @@
+mod aisles;
+mod calendar_page;
+mod data_path;
+mod date;
 mod day_page;
+mod food_table;
 mod generational_map;
 mod page;
+use calendar_page::CalendarPage;
 use color_eyre::Result;
 use day_page::DayPage;
 use page::Page;
+use save_file::{LoadError, LoadFailure, SaveData};
 mod ingrediant;
+mod ingrediant_editor;
+mod ingrediant_line;
+mod legacy;
+mod library;
 mod meal;
 mod meal_editor;
+mod nutrition;
+mod pantry;
 mod picker;
+mod recipe_import;
+mod save_file;
+mod shopping;
+mod stable_id;
 mod unit;
 use crate::picker::PickerState;
-use bincode::{deserialize, serialize};
+use chrono::{Datelike, Local, Weekday};
 use generational_map::GenerationalMap;
 use iced::{
     event,
     keyboard::{self, key::Named, Key},
     theme::{self, Theme},
-    widget::{self, button, column as col, container, row, scrollable, text, text_input, Row},
-    Application, Command, Element, Event, Length, Settings, Subscription,
+    widget::{
+        self, button, checkbox, column as col, container, pick_list, radio, row, scrollable, text,
+        text_input, Row,
+    },
+    window, Application, Command, Element, Event, Length, Settings, Subscription,
 };
 use ingrediant::{Ingrediant, IngrediantKey, IngrediantQuantity};
-use meal::{Meal, MealKey};
+use meal::{Meal, MealKey, PlannedMeal};
 use meal_editor::MealEditorPage;
 use once_cell::sync::Lazy;
+use recipe_import::RecipeImportPage;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
@@ -40,8 +61,11 @@ fn main() -> Result<()> {
     color_eyre::install()?;
 
     Ok(AppState::run(Settings {
+        flags: data_path::resolve(),
         window: iced::window::Settings {
             size: iced::Size::new(300.0, 650.0),
+            // Closing waits for the last changes to be saved
+            exit_on_close_request: false,
             ..Default::default()
         },
         ..Default::default()
@@ -61,23 +85,128 @@ struct LoadingState {
     icon_font_loaded: bool,
 }
 
-type Date = usize;
+type Date = chrono::NaiveDate;
+
+/// How long to wait after the last change before saving, so bursts of changes are saved together
+const SAVE_DELAY: Duration = Duration::from_millis(1500);
+
+/// How many weeks ahead the week view and shopping list cover
+const WEEKS_SHOWN: u64 = 14;
 
-#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+#[derive(Default, Debug, Clone)]
 pub struct State {
     page: Page,
     stack: Vec<Page>,
+    model: Model,
+    meal_creation_input_field: String,
+    /// File the meal library is exported to and imported from
+    library_path: String,
+    /// Outcome of the last library import or export
+    library_status: Option<String>,
+    /// Web page or JSON-LD file a recipe is imported from
+    recipe_path: String,
+    /// Food composition CSV the nutrition table is imported from
+    food_table_path: String,
+    /// The imported nutrition table, read from its file the first time a food is looked up
+    foods: Option<Arc<[food_table::Food]>>,
+    /// Why the nutrition table couldn't be read when a food was looked up
+    food_table_error: Option<String>,
+    shopping_format: shopping::ExportFormat,
+    /// File the shopping list is exported to
+    shopping_path: String,
+    /// Outcome of the last shopping list export
+    shopping_status: Option<String>,
+    /// Leave ticked off ingrediants out of the shopping list
+    hide_checked: bool,
+    shopping_range: shopping::ShoppingRange,
+    preferences: Preferences,
+    pantry_input: String,
+    aisle_input: String,
+    save: SaveState,
+}
+
+/// The user's data, everything in here is saved
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Model {
     days: BTreeMap<Date, Day>,
     meals: GenerationalMap<Meal>,
     ingrediants: GenerationalMap<Ingrediant>,
-    meal_creation_input_field: String,
-    save: SaveState,
+    #[serde(with = "crate::save_file::as_pairs")]
+    shopping_checked: shopping::CheckedItems,
+    /// What is already at home, taken off the shopping list
+    #[serde(with = "crate::save_file::as_pairs")]
+    pantry: BTreeMap<IngrediantKey, IngrediantQuantity>,
+    /// Aisles in the order the shopping list is sorted by
+    aisles: Vec<Arc<str>>,
+    /// The size of the tablespoons the recipes were written with
+    spoon_region: SpoonRegion,
+    /// What to eat in a day, 0 for no target
+    nutrition_targets: nutrition::Nutrients,
+}
+
+/// How the user likes things shown, saved alongside the model
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Preferences {
+    unit_system: UnitSystem,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self {
+            days: BTreeMap::new(),
+            meals: GenerationalMap::default(),
+            ingrediants: GenerationalMap::default(),
+            shopping_checked: shopping::CheckedItems::new(),
+            pantry: BTreeMap::new(),
+            aisles: aisles::default_aisles(),
+            spoon_region: SpoonRegion::default(),
+            nutrition_targets: nutrition::Nutrients::default(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+impl Model {
+    /// The ingrediant called `name`, ignoring case
+    fn ingrediant_named(&self, name: &str) -> Option<IngrediantKey> {
+        self.ingrediants
+            .iter()
+            .find(|(_, ingrediant)| ingrediant.name.eq_ignore_ascii_case(name))
+            .map(|(key, _)| key)
+    }
+
+    /// The ingrediant called `name`, added if there isn't one yet
+    fn ingrediant_named_or_add(&mut self, name: Arc<str>) -> IngrediantKey {
+        match self.ingrediant_named(&name) {
+            Some(key) => key,
+            None => self.ingrediants.push(Ingrediant::new(name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 struct SaveState {
-    saved: bool,
-    saving: bool,
+    /// Bumped every time the model changes
+    changes: u64,
+    /// What `changes` was when the save file was last written
+    saved_changes: u64,
+    /// What `changes` was when the save in progress started
+    saving: Option<u64>,
+    /// The window is waiting on a final save before closing
+    closing: bool,
+    /// Why the last save failed, cleared once a save succeeds
+    error: Option<String>,
+    /// Set when the save file exists but couldn't be read, nothing is saved over it while set
+    load_failure: Option<LoadFailure>,
+    /// Where the state was loaded from and is saved to
+    path: String,
+}
+
+impl SaveState {
+    fn is_saved(&self) -> bool {
+        self.changes == self.saved_changes
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -86,6 +215,8 @@ pub enum Message {
 
     MealAddedToDay(Arc<str>, Date),
     AddDay(Date),
+    OpenDay(Date),
+    ChangeMonth(isize),
     AddMeal,
     AddMealIngrediant,
     AddMealToDay,
@@ -102,12 +233,67 @@ pub enum Message {
         date: Date,
         index: usize,
     },
+    SetMealServings {
+        meal_id: MealKey,
+        servings: u32,
+    },
+    SetPlannedPortions {
+        date: Date,
+        index: usize,
+        portions: u32,
+    },
     RemoveMealIngrediant {
         meal_id: MealKey,
         ingrediant_id: IngrediantKey,
     },
-    Saved(bool),
+    SaveDue(u64),
+    Saved(Result<(), String>),
+    CloseRequested,
+    DiscardUnreadableSave,
+    RestoreBackup(String),
+    BackupRestored(Box<State>),
     SetMealCreationInputFeild(String),
+    SetLibraryPath(String),
+    ExportLibrary,
+    ImportLibrary,
+    LibraryExported(Result<(), String>),
+    LibraryImported(Result<library::Library, String>),
+    SetRecipePath(String),
+    ImportRecipe,
+    RecipeRead(Result<recipe_import::RecipeDraft, String>),
+    EditRecipeDraft(recipe_import::DraftEdit),
+    SaveImportedRecipe,
+    SetShoppingFormat(shopping::ExportFormat),
+    SetShoppingPath(String),
+    ExportShoppingList,
+    CopyShoppingList,
+    ShoppingListExported(Result<(), String>),
+    SetShoppingChecked(IngrediantKey, bool),
+    SetHideChecked(bool),
+    ResetShoppingChecks,
+    SetShoppingRange(shopping::ShoppingRange),
+    SetUnitSystem(UnitSystem),
+    SetSpoonRegion(SpoonRegion),
+    SetNutritionTarget(nutrition::Nutrient, f64),
+    SetFoodTablePath(String),
+    ImportFoodTable,
+    FoodTableRead(Result<Vec<food_table::Food>, String>),
+    LookUpFood,
+    FoodTableLoaded(Result<Vec<food_table::Food>, String>),
+    FoodPicked(Arc<str>),
+    SetPantryInput(String),
+    AddPantryItem,
+    UpdatePantryItem {
+        ingrediant_id: IngrediantKey,
+        field: IngrediantField,
+    },
+    RemovePantryItem(IngrediantKey),
+    SetAisleInput(String),
+    AddAisle,
+    MoveAisle(usize, isize),
+    RemoveAisle(usize),
+    SetIngrediantAisle(IngrediantKey, Option<Arc<str>>),
+    EditIngrediant(ingrediant_editor::IngrediantEdit),
     TabPressed {
         shift: bool,
     },
@@ -117,6 +303,7 @@ pub enum Message {
         field: IngrediantField,
     },
     VerticalMovement(isize),
+    HorizontalMovement(isize),
 
     IngrediantPickedForMeal(Arc<str>, MealKey),
 }
@@ -127,6 +314,87 @@ pub enum IngrediantField {
     Unit(Unit),
 }
 
+impl Message {
+    /// Whether handling this message changes the model, and so should lead to a save
+    fn changes_model(&self) -> bool {
+        match self {
+            Self::MealAddedToDay(..)
+            | Self::AddDay(_)
+            | Self::OpenDay(_)
+            | Self::AddMeal
+            | Self::RemoveMeal(_)
+            | Self::RemoveMealFromDay { .. }
+            | Self::RemoveMealIngrediant { .. }
+            | Self::SetMealServings { .. }
+            | Self::SetPlannedPortions { .. }
+            | Self::UpdateMealIngrediant { .. }
+            | Self::IngrediantPickedForMeal(..)
+            | Self::SaveImportedRecipe
+            | Self::SetShoppingChecked(..)
+            | Self::ResetShoppingChecks
+            | Self::AddPantryItem
+            | Self::UpdatePantryItem { .. }
+            | Self::RemovePantryItem(_)
+            | Self::AddAisle
+            | Self::MoveAisle(..)
+            | Self::RemoveAisle(_)
+            | Self::SetIngrediantAisle(..)
+            | Self::EditIngrediant(_)
+            | Self::SetSpoonRegion(_)
+            | Self::SetNutritionTarget(..)
+            | Self::FoodPicked(_)
+            | Self::BackupRestored(_) => true,
+
+            // Not part of the model but saved with it
+            Self::SetShoppingRange(_) | Self::SetUnitSystem(_) => true,
+
+            Self::LibraryImported(result) => result.is_ok(),
+
+            Self::ClosePicker
+            | Self::ChangeMonth(_)
+            | Self::AddMealIngrediant
+            | Self::AddMealToDay
+            | Self::BackPage
+            | Self::ChangeToPage(_)
+            | Self::AppStateLoaded(_)
+            | Self::MainFontLoaded
+            | Self::IconFontLoaded
+            | Self::MealPickerInput(_)
+            | Self::None
+            | Self::SaveDue(_)
+            | Self::Saved(_)
+            | Self::CloseRequested
+            | Self::DiscardUnreadableSave
+            | Self::RestoreBackup(_)
+            | Self::SetMealCreationInputFeild(_)
+            | Self::SetLibraryPath(_)
+            | Self::ExportLibrary
+            | Self::ImportLibrary
+            | Self::LibraryExported(_)
+            | Self::SetRecipePath(_)
+            | Self::SetFoodTablePath(_)
+            | Self::ImportFoodTable
+            | Self::FoodTableRead(_)
+            | Self::LookUpFood
+            | Self::FoodTableLoaded(_)
+            | Self::ImportRecipe
+            | Self::RecipeRead(_)
+            | Self::EditRecipeDraft(_)
+            | Self::SetShoppingFormat(_)
+            | Self::SetShoppingPath(_)
+            | Self::ExportShoppingList
+            | Self::CopyShoppingList
+            | Self::ShoppingListExported(_)
+            | Self::SetHideChecked(_)
+            | Self::SetPantryInput(_)
+            | Self::SetAisleInput(_)
+            | Self::TabPressed { .. }
+            | Self::VerticalMovement(_)
+            | Self::HorizontalMovement(_) => false,
+        }
+    }
+}
+
 impl Default for IngrediantField {
     fn default() -> Self {
         Self::Unit(Unit::Solid(SolidUnit::Grams))
@@ -135,39 +403,92 @@ impl Default for IngrediantField {
 
 impl State {
     async fn load(path: &str) -> Box<Self> {
-        Box::new(
-            async_std::fs::read(path)
-                .await
-                .ok()
-                .and_then(|bytes| deserialize(&bytes).ok())
-                .unwrap_or_default(),
-        )
+        let mut state = Self::read(path).await;
+        state.save.path = path.to_owned();
+        state.library_path = data_path::export_path("graze-library.json");
+        state.shopping_path = data_path::export_path("graze-shopping.md");
+        state
     }
-    async fn save(self, path: &str) -> bool {
-        println!("Saving");
-        if let Ok(bytes) = serialize(&self) {
-            if async_std::fs::write(path, &bytes).await.is_err() {
-                return false;
+
+    async fn read(path: &str) -> Box<Self> {
+        let bytes = match async_std::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Box::default(),
+            Err(err) => {
+                return Box::new(Self::unloadable(
+                    LoadError::Io(err.to_string()),
+                    None,
+                    save_file::list_backups(path).await,
+                ))
+            }
+        };
+        match save_file::decode(&bytes, save_file::legacy_start(path).await) {
+            Ok(SaveData {
+                model,
+                page,
+                shopping_range,
+                preferences,
+            }) => Box::new(Self {
+                page: page.restore(&model),
+                model,
+                shopping_range,
+                preferences,
+                ..Default::default()
+            }),
+            Err(error) => {
+                let copy = format!("{path}.unreadable-{}", Local::now().format("%Y%m%d-%H%M%S"));
+                let copy = async_std::fs::copy(path, &copy).await.ok().map(|_| copy);
+                Box::new(Self::unloadable(
+                    error,
+                    copy,
+                    save_file::list_backups(path).await,
+                ))
             }
         }
-        async_std::task::sleep(Duration::from_secs(1)).await;
-        true
+    }
+
+    /// An empty state that won't overwrite the save file it failed to load
+    fn unloadable(error: LoadError, copy: Option<String>, backups: Vec<String>) -> Self {
+        let mut state = Self::default();
+        state.save.load_failure = Some(LoadFailure {
+            error,
+            copy,
+            backups,
+        });
+        state
+    }
+
+    fn save_data(&self) -> SaveData {
+        SaveData {
+            model: self.model.clone(),
+            page: self.page.saved(),
+            shopping_range: self.shopping_range,
+            preferences: self.preferences,
+        }
+    }
+
+    async fn save(data: SaveData, path: &str) -> Result<(), String> {
+        let bytes = save_file::encode(&data).map_err(|err| err.to_string())?;
+        let now = Local::now().naive_local();
+        save_file::write(path, &bytes, save_file::backup_count(), now)
+            .await
+            .map_err(|err| format!("couldn't write {path}: {err}"))
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 struct Day {
     date: Date,
-    meals: Vec<MealKey>,
+    meals: Vec<PlannedMeal>,
 }
 
 impl Application for AppState {
     type Executor = iced::executor::Default;
     type Message = Message;
     type Theme = iced::Theme;
-    type Flags = ();
+    type Flags = String;
 
-    fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
+    fn new(data_path: Self::Flags) -> (Self, Command<Self::Message>) {
         (
             Self::Loading(LoadingState {
                 app_state: None,
@@ -175,7 +496,7 @@ impl Application for AppState {
                 icon_font_loaded: false,
             }),
             Command::batch([
-                Command::perform(State::load("./data"), |state| {
+                Command::perform(async move { State::load(&data_path).await }, |state| {
                     Message::AppStateLoaded(state)
                 }),
                 iced::font::load(include_bytes!("../fonts/icons.ttf").as_slice())
@@ -189,7 +510,7 @@ impl Application for AppState {
     fn title(&self) -> String {
         match self {
             Self::Loaded(state) => {
-                let save_indicator = if state.save.saved { "" } else { "*" };
+                let save_indicator = if state.save.is_saved() { "" } else { "*" };
                 let mut title = "Graze".to_owned();
                 title.push_str(save_indicator);
                 title
@@ -215,6 +536,7 @@ impl Application for AppState {
                     Message::AppStateLoaded(state) => {
                         load_state.app_state = Some(state);
                     }
+                    Message::CloseRequested => return window::close(window::Id::MAIN),
                     _ => unreachable!(),
                 }
 
@@ -239,15 +561,30 @@ impl Application for AppState {
             Self::Loaded(state) => {
                 let page: Element<Message> = match &state.page {
                     Page::MealList => meal_list_view(state).into(),
+                    Page::CalendarView(calendar) => calendar.view(state),
                     Page::DayView(day_page) => day_page.view(state),
                     Page::MealEditorView(page) => page.view(&state),
+                    Page::RecipeImportView(page) => page.view(state),
+                    Page::PantryView => pantry::view(state),
+                    Page::AislesView => aisles::view(state),
+                    Page::IngrediantEditorView(page) => page.view(state),
                     Page::WeekView(range) => week_view(state, range).into(),
-                    Page::ShoppingView { from, until } => {
-                        shopping_view(state, *from, *until).into()
-                    } // Page::MealPicker => meal_picker_view(state),
+                    Page::ShoppingView => shopping_view(state),
+                    // Page::MealPicker => meal_picker_view(state),
                 };
 
-                col![bar_view(state), page]
+                let load_failure = state.save.load_failure.as_ref().map(load_failure_view);
+                let save_error = state.save.error.as_ref().map(|err| {
+                    container(text(format!("Your plan couldn't be saved, {err}.")))
+                        .style(theme::Container::Box)
+                        .padding(10)
+                        .width(Length::Fill)
+                });
+
+                col![bar_view(state)]
+                    .push_maybe(load_failure)
+                    .push_maybe(save_error)
+                    .push(page)
                     .height(Length::Fill)
                     .width(Length::Fill)
                     .spacing(10)
@@ -299,9 +636,16 @@ impl Application for AppState {
                     Key::Named(Named::ArrowUp) | Key::Character("K") => {
                         Some(Message::VerticalMovement(-1))
                     }
+                    Key::Named(Named::ArrowLeft) | Key::Character("H") => {
+                        Some(Message::HorizontalMovement(-1))
+                    }
+                    Key::Named(Named::ArrowRight) | Key::Character("L") => {
+                        Some(Message::HorizontalMovement(1))
+                    }
                     _ => None,
                 }
             }
+            (Event::Window(_, window::Event::CloseRequested), _) => Some(Message::CloseRequested),
 
             _ => None,
         })
@@ -313,7 +657,7 @@ impl Application for AppState {
 }
 
 fn update_ui(state: &mut State, message: Message) -> Command<Message> {
-    state.save.saved = false;
+    let changes_model = message.changes_model();
     let com = match message {
         Message::MealPickerInput(input) => on_message_meal_picker_input(state, input),
         Message::AddMeal => on_message_add_meal(state),
@@ -323,6 +667,19 @@ fn update_ui(state: &mut State, message: Message) -> Command<Message> {
         } => on_message_remove_meal_ingrediant(state, meal_id, ingrediant_id),
         Message::ChangeToPage(page) => on_message_change_page(page, state),
         Message::AddDay(date) => on_message_add_day(state, date),
+        Message::OpenDay(date) => {
+            state.model.days.entry(date).or_insert_with(|| Day {
+                date,
+                meals: Vec::new(),
+            });
+            on_message_change_page(Page::DayView(DayPage::new(date)), state)
+        }
+        Message::ChangeMonth(offset) => {
+            if let Page::CalendarView(ref mut calendar) = state.page {
+                calendar.change_month(offset);
+            }
+            Command::none()
+        }
         Message::AddMealToDay => on_message_add_meal_to_day(state),
         Message::BackPage => {
             back_page(state);
@@ -334,26 +691,300 @@ fn update_ui(state: &mut State, message: Message) -> Command<Message> {
             field,
         } => on_message_update_meal_ingrediant(state, meal_name_hash, ingrediant_id, field),
         Message::AddMealIngrediant => on_message_add_meal_ingrediant(state),
-        Message::Saved(succes) => {
-            state.save.saved = succes;
-            state.save.saving = false;
+        Message::SaveDue(changes) => {
+            if changes == state.save.changes {
+                start_save(state)
+            } else {
+                Command::none()
+            }
+        }
+        Message::Saved(result) => on_saved(state, result),
+        Message::CloseRequested => {
+            if state.save.is_saved() {
+                return window::close(window::Id::MAIN);
+            }
+            state.save.closing = true;
+            start_save(state)
+        }
+        Message::DiscardUnreadableSave => {
+            state.save.load_failure = None;
+            Command::none()
+        }
+        Message::RestoreBackup(backup) => Command::perform(
+            async move { State::load(&backup).await },
+            Message::BackupRestored,
+        ),
+        Message::BackupRestored(restored) => {
+            let previous = state.save.load_failure.take();
+            let path = take(&mut state.save.path);
+            *state = *restored;
+            state.save.path = path;
+            // Keep offering the other backups if this one couldn't be read either
+            if let (Some(failure), Some(previous)) = (&mut state.save.load_failure, previous) {
+                failure.backups = previous.backups;
+            }
             Command::none()
         }
         Message::SetMealCreationInputFeild(input) => {
             state.meal_creation_input_field = input;
             Command::none()
         }
+        Message::SetLibraryPath(path) => {
+            state.library_path = path;
+            Command::none()
+        }
+        Message::ExportLibrary => Command::perform(
+            library::write(
+                state.library_path.clone(),
+                library::Library::export(&state.model),
+            ),
+            Message::LibraryExported,
+        ),
+        Message::ImportLibrary => Command::perform(
+            library::read(state.library_path.clone()),
+            Message::LibraryImported,
+        ),
+        Message::LibraryExported(result) => {
+            state.library_status = Some(match result {
+                Ok(()) => format!("Exported to {}", state.library_path),
+                Err(err) => err,
+            });
+            Command::none()
+        }
+        Message::LibraryImported(result) => {
+            let result = result.and_then(|library| library.merge_into(&mut state.model));
+            state.library_status = Some(match result {
+                Ok(()) => format!("Imported {}", state.library_path),
+                Err(err) => err,
+            });
+            Command::none()
+        }
+        Message::SetRecipePath(path) => {
+            state.recipe_path = path;
+            Command::none()
+        }
+        Message::ImportRecipe => Command::perform(
+            recipe_import::read(state.recipe_path.clone()),
+            Message::RecipeRead,
+        ),
+        Message::RecipeRead(result) => match result {
+            Ok(draft) => {
+                state.library_status = None;
+                on_message_change_page(Page::RecipeImportView(RecipeImportPage::new(draft)), state)
+            }
+            Err(err) => {
+                state.library_status = Some(err);
+                Command::none()
+            }
+        },
+        Message::EditRecipeDraft(edit) => {
+            if let Page::RecipeImportView(ref mut page) = state.page {
+                page.draft.edit(edit);
+            }
+            Command::none()
+        }
+        Message::SaveImportedRecipe => {
+            if let Page::RecipeImportView(ref mut page) = state.page {
+                // The button is disabled while lines can't be added up, they stay on the page
+                if let Ok(meal_id) = page.draft.clone().add_to(&mut state.model) {
+                    // The editor takes the review page's place, so going back leads to the meal
+                    // list
+                    state.page = Page::MealEditorView(MealEditorPage::new(meal_id));
+                }
+            }
+            Command::none()
+        }
+        Message::SetShoppingFormat(format) => {
+            // Keep the file extension in step with the format unless the user picked their own
+            let path = std::path::Path::new(&state.shopping_path);
+            let known = shopping::ExportFormat::ALL.map(shopping::ExportFormat::extension);
+            if path
+                .extension()
+                .is_some_and(|extension| known.iter().any(|known| extension == *known))
+            {
+                state.shopping_path = path
+                    .with_extension(format.extension())
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            state.shopping_format = format;
+            Command::none()
+        }
+        Message::SetShoppingPath(path) => {
+            state.shopping_path = path;
+            Command::none()
+        }
+        Message::ExportShoppingList => match shopping_export(state) {
+            Some(contents) => Command::perform(
+                shopping::write(state.shopping_path.clone(), contents),
+                Message::ShoppingListExported,
+            ),
+            None => Command::none(),
+        },
+        Message::CopyShoppingList => match shopping_export(state) {
+            Some(contents) => {
+                state.shopping_status = Some("Copied to the clipboard".to_owned());
+                iced::clipboard::write(contents)
+            }
+            None => Command::none(),
+        },
+        Message::ShoppingListExported(result) => {
+            state.shopping_status = Some(match result {
+                Ok(()) => format!("Saved to {}", state.shopping_path),
+                Err(err) => err,
+            });
+            Command::none()
+        }
+        Message::SetShoppingChecked(ingrediant, is_checked) => {
+            shopping::set_checked(
+                &mut state.model.shopping_checked,
+                state.shopping_range.dates(date::today()),
+                ingrediant,
+                is_checked,
+                date::today(),
+            );
+            Command::none()
+        }
+        Message::SetHideChecked(hide) => {
+            state.hide_checked = hide;
+            Command::none()
+        }
+        Message::ResetShoppingChecks => {
+            let Range { start, end } = state.shopping_range.dates(date::today());
+            state.model.shopping_checked.remove(&(start, end));
+            Command::none()
+        }
+        Message::SetShoppingRange(range) => {
+            state.shopping_range = range;
+            Command::none()
+        }
+        Message::SetUnitSystem(system) => {
+            state.preferences.unit_system = system;
+            Command::none()
+        }
+        Message::SetSpoonRegion(region) => {
+            state.model.spoon_region = region;
+            Command::none()
+        }
+        Message::SetNutritionTarget(nutrient, target) => {
+            *state.model.nutrition_targets.get_mut(nutrient) = target;
+            Command::none()
+        }
+        Message::SetFoodTablePath(path) => {
+            state.food_table_path = path;
+            Command::none()
+        }
+        Message::ImportFoodTable => Command::perform(
+            food_table::import(
+                state.food_table_path.clone(),
+                food_table::table_path(&state.save.path),
+            ),
+            Message::FoodTableRead,
+        ),
+        Message::FoodTableRead(result) => {
+            state.library_status = Some(match result {
+                Ok(foods) => {
+                    let status = format!("Imported {} foods", foods.len());
+                    state.foods = Some(foods.into());
+                    state.food_table_error = None;
+                    status
+                }
+                Err(err) => err,
+            });
+            Command::none()
+        }
+        Message::LookUpFood => match (&mut state.page, &state.foods) {
+            (Page::IngrediantEditorView(page), Some(foods)) => page.open_food_picker(foods),
+            (Page::IngrediantEditorView(_), None) => Command::perform(
+                food_table::load(food_table::table_path(&state.save.path)),
+                Message::FoodTableLoaded,
+            ),
+            _ => Command::none(),
+        },
+        Message::FoodTableLoaded(result) => match result {
+            Ok(foods) => {
+                state.food_table_error = None;
+                let foods = state.foods.insert(foods.into());
+                match state.page {
+                    Page::IngrediantEditorView(ref mut page) => page.open_food_picker(foods),
+                    _ => Command::none(),
+                }
+            }
+            Err(err) => {
+                state.food_table_error = Some(err);
+                Command::none()
+            }
+        },
+        Message::FoodPicked(name) => {
+            if let (Page::IngrediantEditorView(ref mut page), Some(foods)) =
+                (&mut state.page, &state.foods)
+            {
+                page.link_food(&mut state.model, foods, &name);
+            }
+            Command::none()
+        }
+        Message::SetPantryInput(input) => {
+            state.pantry_input = input;
+            Command::none()
+        }
+        Message::AddPantryItem => {
+            if pantry::add_line(&mut state.model, &state.pantry_input) {
+                state.pantry_input.clear();
+            }
+            Command::none()
+        }
+        Message::UpdatePantryItem {
+            ingrediant_id,
+            field,
+        } => {
+            pantry::update(&mut state.model, ingrediant_id, field);
+            Command::none()
+        }
+        Message::RemovePantryItem(ingrediant_id) => {
+            state.model.pantry.remove(&ingrediant_id);
+            Command::none()
+        }
+        Message::SetAisleInput(input) => {
+            state.aisle_input = input;
+            Command::none()
+        }
+        Message::AddAisle => {
+            if aisles::add(&mut state.model, &state.aisle_input) {
+                state.aisle_input.clear();
+            }
+            Command::none()
+        }
+        Message::MoveAisle(index, offset) => {
+            aisles::move_by(&mut state.model, index, offset);
+            Command::none()
+        }
+        Message::RemoveAisle(index) => {
+            aisles::remove(&mut state.model, index);
+            Command::none()
+        }
+        Message::SetIngrediantAisle(ingrediant_id, category) => {
+            if let Some(ingrediant) = state.model.ingrediants.get_mut(ingrediant_id) {
+                ingrediant.category = category;
+            }
+            Command::none()
+        }
+        Message::EditIngrediant(edit) => {
+            if let Page::IngrediantEditorView(ref mut page) = state.page {
+                page.edit(&mut state.model, edit);
+            }
+            Command::none()
+        }
         // Message::MealPickerSubmit(meal_id) => {
         //     state.picker_state.selected_id = meal_id;
         //     back_page(state);
         //     Command::none()
         // }
         Message::RemoveMeal(id) => {
-            state.meals.remove(id);
+            state.model.meals.remove(id);
             Command::none()
         }
         Message::TabPressed { shift } => {
-            state.page.as_any().on_tab(shift)
+            state.page.on_tab(shift)
             // if let Some(picker) = active_picker_mut(state) {
             //     picker.fill_input();
             //     Command::none()
@@ -366,16 +997,43 @@ fn update_ui(state: &mut State, message: Message) -> Command<Message> {
             // }
         }
         Message::None => Command::none(),
-        Message::RemoveMealFromDay { date, index } => {
-            state.days.get_mut(&date).map(|day| day.meals.remove(index));
+        Message::SetMealServings { meal_id, servings } => {
+            if let Some(meal) = state.model.meals.get_mut(meal_id) {
+                meal.servings = servings;
+            }
             Command::none()
         }
-        Message::VerticalMovement(movement) => {
-            if let Some(picker) = active_picker_mut(state) {
-                picker.vertical_movement(movement);
+        Message::SetPlannedPortions {
+            date,
+            index,
+            portions,
+        } => {
+            if let Some(planned) = state
+                .model
+                .days
+                .get_mut(&date)
+                .and_then(|day| day.meals.get_mut(index))
+            {
+                planned.portions = portions.max(1);
             }
             Command::none()
-        } // _ => Command::none(),
+        }
+        Message::RemoveMealFromDay { date, index } => {
+            state
+                .model
+                .days
+                .get_mut(&date)
+                .map(|day| day.meals.remove(index));
+            Command::none()
+        }
+        Message::VerticalMovement(movement) => state
+            .page
+            .as_any_mut()
+            .map_or_else(Command::none, |page| page.vertical_movement(movement)),
+        Message::HorizontalMovement(movement) => state
+            .page
+            .as_any_mut()
+            .map_or_else(Command::none, |page| page.horizontal_movement(movement)), // _ => Command::none(),
         Message::AppStateLoaded(_) | Message::MainFontLoaded | Message::IconFontLoaded => {
             unreachable!()
         }
@@ -385,22 +1043,83 @@ fn update_ui(state: &mut State, message: Message) -> Command<Message> {
         Message::ClosePicker => {
             match state.page {
                 Page::MealEditorView(ref mut editor) => editor.close_picker(),
+                Page::IngrediantEditorView(ref mut editor) => editor.close_picker(),
                 _ => {}
             };
             Command::none()
         }
         Message::MealAddedToDay(name, date) => on_meal_picked_for_date(state, name, date),
     };
-    let save_com = if state.save.saving || state.save.saved {
-        Command::none()
+    let save_com = if changes_model {
+        state.save.changes += 1;
+        let changes = state.save.changes;
+        Command::perform(async_std::task::sleep(SAVE_DELAY), move |_| {
+            Message::SaveDue(changes)
+        })
     } else {
-        let copy = state.clone();
-        state.save.saving = true;
-        Command::perform(copy.save("./data"), Message::Saved)
+        Command::none()
     };
     Command::batch([com, save_com])
 }
 
+/// Writes the save file unless a save is already under way, when the window is closing it is
+/// closed once the save is done
+fn start_save(state: &mut State) -> Command<Message> {
+    if state.save.saving.is_some() {
+        return Command::none();
+    }
+    if state.save.load_failure.is_some() {
+        return close_if_closing(state);
+    }
+    let data = state.save_data();
+    state.save.saving = Some(state.save.changes);
+    let path = state.save.path.clone();
+    Command::perform(
+        async move { State::save(data, &path).await },
+        Message::Saved,
+    )
+}
+
+/// Records the outcome of a save. A window waiting to close is kept open if the save failed, so
+/// the error can be seen and nothing is lost.
+fn on_saved(state: &mut State, result: Result<(), String>) -> Command<Message> {
+    let saving = state.save.saving.take();
+    match result {
+        Ok(()) => {
+            state.save.saved_changes = saving.unwrap_or(state.save.saved_changes);
+            state.save.error = None;
+        }
+        Err(err) => {
+            state.save.error = Some(err);
+            state.save.closing = false;
+        }
+    }
+    if state.save.closing {
+        // Changes made while the last save was being written still need saving
+        return if state.save.is_saved() {
+            close_if_closing(state)
+        } else {
+            start_save(state)
+        };
+    }
+    if state.save.is_saved() {
+        Command::none()
+    } else {
+        let changes = state.save.changes;
+        Command::perform(async_std::task::sleep(SAVE_DELAY), move |_| {
+            Message::SaveDue(changes)
+        })
+    }
+}
+
+fn close_if_closing(state: &State) -> Command<Message> {
+    if state.save.closing {
+        window::close(window::Id::MAIN)
+    } else {
+        Command::none()
+    }
+}
+
 fn on_ingrediant_picked_for_meal(
     state: &mut State,
     name: Arc<str>,
@@ -410,24 +1129,40 @@ fn on_ingrediant_picked_for_meal(
         return Command::none();
     };
 
-    let ingredaint_key = state
-        .ingrediants
-        .iter()
-        .find(|(_key, ing)| *name == *ing.name)
-        .map(|(key, _)| key);
-    let ingredaint_key =
-        ingredaint_key.unwrap_or_else(|| state.ingrediants.push(Ingrediant { name: name.into() }));
+    // A picked name is used as is, anything else typed with a quantity or unit in front is read as
+    // a line like "2 tbsp olive oil" or "a pinch of salt". Names such as "Pound cake" are kept whole.
+    let line = ingrediant_line::parse(&name);
+    let (name, quantity) = if state.model.ingrediant_named(&name).is_some()
+        || !ingrediant_line::has_quantity(&name)
+        || line.name.is_empty()
+    {
+        (name, None)
+    } else {
+        let quantity = (line.quantity > 0.0).then_some(IngrediantQuantity {
+            quantity: line.quantity,
+            unit: line.unit,
+        });
+        (line.name.into(), quantity)
+    };
+    let ingredaint_key = state.model.ingrediant_named_or_add(name);
 
-    let Some(meal) = state.meals.get_mut(meal_id) else {
+    let Some(meal) = state.model.meals.get_mut(meal_id) else {
         return Command::none();
     };
 
-    meal.ingrediants
-        .entry(ingredaint_key)
-        .or_insert(IngrediantQuantity {
-            quantity: 0.0,
-            unit: Unit::default(),
-        });
+    match quantity {
+        Some(quantity) => {
+            meal.ingrediants.insert(ingredaint_key, quantity);
+        }
+        None => {
+            meal.ingrediants
+                .entry(ingredaint_key)
+                .or_insert(IngrediantQuantity {
+                    quantity: 0.0,
+                    unit: Unit::default(),
+                });
+        }
+    }
 
     editor.close_picker();
 
@@ -441,19 +1176,23 @@ fn on_meal_picked_for_date(state: &mut State, name: Arc<str>, date: Date) -> Com
     };
 
     let meal_key = state
+        .model
         .meals
         .iter()
         .find(|(_key, ing)| *name == *ing.name)
         .map(|(key, _)| key);
-    let meal_key = meal_key.unwrap_or_else(|| {
-        state.meals.push(Meal {
-            name,
-            ingrediants: BTreeMap::new(),
-        })
-    });
+    let meal_key = meal_key.unwrap_or_else(|| state.model.meals.push(Meal::new(name)));
 
-    if let Some(day) = state.days.get_mut(&date) {
-        day.meals.push(meal_key);
+    let portions = state
+        .model
+        .meals
+        .get(meal_key)
+        .map_or(1, |meal| meal.servings);
+    if let Some(day) = state.model.days.get_mut(&date) {
+        day.meals.push(PlannedMeal {
+            meal_id: meal_key,
+            portions,
+        });
         editor.close_picker();
     }
 
@@ -463,7 +1202,7 @@ fn on_meal_picked_for_date(state: &mut State, name: Arc<str>, date: Date) -> Com
 
 fn on_message_add_meal_ingrediant(state: &mut State) -> Command<Message> {
     // if let (Some(meal), Some(ingrediant)) = (
-    //     state.meals.get_mut(meal_id),
+    //     state.model.meals.get_mut(meal_id),
     //     state
     //         .ingrediants
     //         .iter()
@@ -479,7 +1218,7 @@ fn on_message_add_meal_ingrediant(state: &mut State) -> Command<Message> {
 
     match state.page {
         Page::MealEditorView(ref mut meal_editor) => {
-            meal_editor.open_ingrediant_picker(&state.ingrediants)
+            meal_editor.open_ingrediant_picker(&state.model.ingrediants)
         }
 
         _ => Command::none(),
@@ -489,13 +1228,13 @@ fn on_message_add_meal_ingrediant(state: &mut State) -> Command<Message> {
 fn on_message_add_meal_to_day(state: &mut State) -> Command<Message> {
     println!("Open sessemy");
     match state.page {
-        Page::DayView(ref mut page) => page.open_meal_picker(&state.meals),
+        Page::DayView(ref mut page) => page.open_meal_picker(&state.model.meals),
         _ => unreachable!(),
     }
 }
 
 fn on_message_add_day(state: &mut State, date: Date) -> Command<Message> {
-    state.days.insert(
+    state.model.days.insert(
         date,
         Day {
             date,
@@ -524,27 +1263,26 @@ fn on_message_remove_meal_ingrediant(
     meal_name_hash: MealKey,
     ingrediant_id: IngrediantKey,
 ) -> Command<Message> {
-    if let Some(meal) = state.meals.get_mut(meal_name_hash) {
+    if let Some(meal) = state.model.meals.get_mut(meal_name_hash) {
         meal.ingrediants.remove(&ingrediant_id);
     }
     Command::none()
 }
 
 fn on_message_add_meal(state: &mut State) -> Command<Message> {
-    state.meals.push(Meal {
-        name: state.meal_creation_input_field.clone().into(),
-        ingrediants: BTreeMap::new(),
-    });
+    state
+        .model
+        .meals
+        .push(Meal::new(state.meal_creation_input_field.clone().into()));
     state.meal_creation_input_field = String::new();
     Command::none()
 }
 
 fn on_message_meal_picker_input(state: &mut State, input: String) -> Command<Message> {
-    if let Some(picker) = active_picker_mut(state) {
-        picker.input(input);
-    }
-
-    Command::none()
+    state
+        .page
+        .as_any_mut()
+        .map_or_else(Command::none, |page| page.picker_input(input))
 }
 
 fn on_message_update_meal_ingrediant(
@@ -553,7 +1291,7 @@ fn on_message_update_meal_ingrediant(
     ingrediant_id: IngrediantKey,
     field: IngrediantField,
 ) -> Command<Message> {
-    if let Some(meal) = state.meals.get_mut(meal_id) {
+    if let Some(meal) = state.model.meals.get_mut(meal_id) {
         if let Some(IngrediantQuantity {
             ref mut quantity,
             // quantity_input,
@@ -581,53 +1319,197 @@ fn back_page(state: &mut State) {
     }
 }
 
-fn shopping_view<'a>(state: &State, from: Date, until: Date) -> Element<'a, Message> {
-    let mut meals_and_count = BTreeMap::new();
-    for (_, day) in state.days.range(from..until) {
-        for meal_id in &day.meals {
-            if let Some(count) = meals_and_count.get_mut(meal_id) {
-                *count += 1.0;
-            } else {
-                meals_and_count.insert(*meal_id, 1.0);
-            }
-        }
+/// The shopping list on the current page in the chosen export format
+fn shopping_export(state: &State) -> Option<String> {
+    if !matches!(state.page, Page::ShoppingView) {
+        return None;
     }
-    let mut list: BTreeMap<IngrediantKey, (f64, Unit)> = BTreeMap::new();
-    for (meal_name, count) in meals_and_count {
-        let Some(meal) = state.meals.get(meal_name) else {
-            continue;
-        };
+    let dates = state.shopping_range.dates(date::today());
+    let lines = shopping::lines(
+        &state.model,
+        shopping::to_buy(&state.model, dates),
+        state.preferences.unit_system,
+    );
+    Some(shopping::export(&lines, state.shopping_format))
+}
 
-        for (ingrediant_id, IngrediantQuantity { quantity, unit }) in meal.ingrediants.iter() {
-            let to_merge = list.entry(*ingrediant_id).or_insert((0.0, Unit::default()));
-            merge_into_unit(to_merge, *quantity, *unit);
-        }
-    }
+fn shopping_view(state: &State) -> Element<'_, Message> {
+    let Range { start, end } = state.shopping_range.dates(date::today());
+    let list = shopping::to_buy(&state.model, start..end);
 
     let header = text("Shopping").size(30);
 
-    let format_list_item = |(id, (ammount, unit)): (IngrediantKey, (f64, Unit))| {
-        state.ingrediants.get(id).map(|ingrediant| {
-            let content = format!(
-                "{}: {} {}",
-                ingrediant.name,
-                (ammount * 10.).round() / 10.,
-                unit.abreviation()
-            );
-            text(content).into()
+    let checked = state.model.shopping_checked.get(&(start, end));
+    let is_checked = |line: &shopping::ShoppingLine| {
+        checked.is_some_and(|checked| checked.contains(&line.ingrediant))
+    };
+    let lines: Vec<_> = shopping::lines(&state.model, list, state.preferences.unit_system)
+        .into_iter()
+        .filter(|line| !(state.hide_checked && is_checked(line)))
+        .collect();
+    let ingrediant_list = col(shopping::groups(&lines).map(|group| {
+        let aisle = group[0].category.as_deref().unwrap_or("Other");
+        col![text(aisle).size(20)]
+            .extend(group.iter().map(|line| {
+                let ingrediant = line.ingrediant;
+                checkbox(
+                    format!("{}: {}", line.name, line.ammounts_label()),
+                    is_checked(line),
+                )
+                .on_toggle(move |is_checked| Message::SetShoppingChecked(ingrediant, is_checked))
+                .into()
+            }))
+            .spacing(5)
+            .into()
+    }))
+    .spacing(10);
+
+    let controls = row![
+        checkbox("Hide checked", state.hide_checked).on_toggle(Message::SetHideChecked),
+        col![].width(Length::Fill),
+        button("Aisles").on_press(Message::ChangeToPage(Page::AislesView)),
+        button("Reset").on_press_maybe(checked.map(|_| Message::ResetShoppingChecks)),
+    ]
+    .spacing(5)
+    .align_items(iced::Alignment::Center);
+
+    scrollable(
+        col![
+            header,
+            shopping_range_view(state),
+            controls,
+            ingrediant_list,
+            shopping_export_view(state)
+        ]
+        .spacing(10),
+    )
+    .into()
+}
+
+fn shopping_range_view(state: &State) -> Element<'_, Message> {
+    let range = state.shopping_range;
+    let today = date::today();
+    let kinds = row(shopping::RangeKind::ALL.map(|kind| {
+        radio(kind.to_string(), kind, Some(range.kind()), move |kind| {
+            Message::SetShoppingRange(range.with_kind(kind, today))
         })
+        .into()
+    }))
+    .spacing(10);
+
+    let detail: Element<Message> = match range {
+        shopping::ShoppingRange::ThisWeek => col![].into(),
+        shopping::ShoppingRange::NextDays(days) => row![
+            text("Days").width(Length::Fill),
+            iced_aw::number_input(days, 365, |days| {
+                Message::SetShoppingRange(shopping::ShoppingRange::NextDays(days))
+            })
+            .min(1)
+        ]
+        .align_items(iced::Alignment::Center)
+        .into(),
+        shopping::ShoppingRange::Custom { from, until } => col![
+            date_stepper("From", from, move |from| shopping::ShoppingRange::Custom {
+                from,
+                until: until.max(from)
+            }),
+            date_stepper("Until", until, move |until| {
+                shopping::ShoppingRange::Custom {
+                    from: from.min(until),
+                    until,
+                }
+            }),
+        ]
+        .spacing(5)
+        .into(),
+    };
+
+    let Range { start, end } = range.dates(today);
+    let covered = match end.pred_opt().filter(|last| *last > start) {
+        Some(last) => format!(
+            "{} to {}",
+            date::short_label(start),
+            date::short_label(last)
+        ),
+        None => date::short_label(start),
     };
-    let ingrediant_list = col(list.into_iter().flat_map(format_list_item));
-    scrollable(col![header, ingrediant_list]).into()
+
+    col![kinds, detail, text(covered)].spacing(5).into()
 }
 
-fn merge_into_unit(to_merge: &mut (f64, Unit), quantity: f64, unit: Unit) {
-    todo!()
+/// Shows `date` with buttons to move it a day either way
+fn date_stepper<'a>(
+    label: &'a str,
+    date: Date,
+    range: impl Fn(Date) -> shopping::ShoppingRange,
+) -> Element<'a, Message> {
+    let step = |date: Option<Date>| date.map(|date| Message::SetShoppingRange(range(date)));
+    row![
+        text(label).width(Length::Fill),
+        button("<").on_press_maybe(step(date.pred_opt())),
+        text(date::short_label(date)),
+        button(">").on_press_maybe(step(date.succ_opt())),
+    ]
+    .spacing(5)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+fn shopping_export_view(state: &State) -> Element<'_, Message> {
+    let format = pick_list(
+        shopping::ExportFormat::ALL,
+        Some(state.shopping_format),
+        Message::SetShoppingFormat,
+    );
+    let controls = row![
+        text_input("Export file", &state.shopping_path).on_input(Message::SetShoppingPath),
+        button("Save").on_press(Message::ExportShoppingList),
+        button("Copy").on_press(Message::CopyShoppingList),
+    ]
+    .spacing(5);
+    container(
+        col![text("Export").size(20), format, controls]
+            .push_maybe(state.shopping_status.as_deref().map(text))
+            .spacing(5),
+    )
+    .style(theme::Container::Box)
+    .padding(5)
+    .width(Length::Fill)
+    .into()
+}
+
+fn load_failure_view(failure: &LoadFailure) -> Element<'_, Message> {
+    let kept = failure.copy.as_ref().map_or_else(
+        || "A copy could not be made.".to_owned(),
+        |copy| format!("A copy was kept at {copy}."),
+    );
+    container(
+        col![
+            text(format!("Your plan couldn't be loaded, {}.", failure.error)),
+            text(kept),
+            text("Nothing will be saved until you restore a backup or start fresh."),
+        ]
+        .extend(failure.backups.iter().map(|backup| {
+            row![
+                text(backup).width(Length::Fill),
+                button("Restore").on_press(Message::RestoreBackup(backup.clone())),
+            ]
+            .spacing(5)
+            .align_items(iced::Alignment::Center)
+            .into()
+        }))
+        .push(button("Start fresh").on_press(Message::DiscardUnreadableSave))
+        .spacing(5),
+    )
+    .style(theme::Container::Box)
+    .padding(10)
+    .width(Length::Fill)
+    .into()
 }
 
 fn meal_list_view<'a>(state: &'a State) -> Element<'a, Message> {
-    let mut list = Vec::with_capacity(state.meals.len());
-    for (id, meal) in state.meals.iter() {
+    let mut list = Vec::with_capacity(state.model.meals.len());
+    for (id, meal) in state.model.meals.iter() {
         list.push(meal::meal_row_view(meal, id, Message::RemoveMeal(id)));
     }
     scrollable(
@@ -641,20 +1523,90 @@ fn meal_list_view<'a>(state: &'a State) -> Element<'a, Message> {
                 button("Add").on_press(Message::AddMeal)
             ]
             .spacing(10),
+            library_view(state),
+            preferences_view(state),
         ]
         .spacing(10),
     )
     .into()
 }
 
+fn library_view(state: &State) -> Element<'_, Message> {
+    let controls = row![
+        text_input("Library file", &state.library_path).on_input(Message::SetLibraryPath),
+        button("Export").on_press(Message::ExportLibrary),
+        button("Import").on_press(Message::ImportLibrary),
+    ]
+    .spacing(5);
+    let recipe = row![
+        text_input("Recipe web page or JSON-LD file", &state.recipe_path)
+            .on_input(Message::SetRecipePath)
+            .on_submit(Message::ImportRecipe),
+        button("Import recipe").on_press(Message::ImportRecipe),
+    ]
+    .spacing(5);
+    let foods = row![
+        text_input("Food composition CSV", &state.food_table_path)
+            .on_input(Message::SetFoodTablePath)
+            .on_submit(Message::ImportFoodTable),
+        button("Import foods").on_press(Message::ImportFoodTable),
+    ]
+    .spacing(5);
+    container(
+        col![text("Library").size(20), controls, recipe, foods]
+            .push_maybe(state.library_status.as_deref().map(text))
+            .spacing(5),
+    )
+    .style(theme::Container::Box)
+    .padding(5)
+    .width(Length::Fill)
+    .into()
+}
+
+fn preferences_view(state: &State) -> Element<'_, Message> {
+    let units = row![
+        text("Units").width(Length::Fill),
+        pick_list(
+            UnitSystem::ALL,
+            Some(state.preferences.unit_system),
+            Message::SetUnitSystem
+        ),
+    ]
+    .spacing(5)
+    .align_items(iced::Alignment::Center);
+    let spoons = row![
+        text("Tablespoons").width(Length::Fill),
+        pick_list(
+            SpoonRegion::ALL,
+            Some(state.model.spoon_region),
+            Message::SetSpoonRegion
+        ),
+    ]
+    .spacing(5)
+    .align_items(iced::Alignment::Center);
+    container(
+        col![
+            text("Preferences").size(20),
+            units,
+            spoons,
+            nutrition::targets_view(state)
+        ]
+        .spacing(5),
+    )
+    .style(theme::Container::Box)
+    .padding(5)
+    .width(Length::Fill)
+    .into()
+}
+
 fn week_view<'a>(state: &State, range: &Range<Date>) -> Element<'a, Message> {
     let mut week_start = range.start;
     let mut weeks = Vec::new();
     let mut week = Vec::new();
-    let mut push_week = |week, week_start| {
+    let mut push_week = |week, week_start: Date| {
         weeks.push(
             col![
-                text(format!("Week {}", week_start / 7)).size(30),
+                text(format!("Week {}", week_start.iso_week().week())).size(30),
                 container(
                     container(col(week).spacing(5))
                         .style(theme::Container::Box)
@@ -667,11 +1619,16 @@ fn week_view<'a>(state: &State, range: &Range<Date>) -> Element<'a, Message> {
             .into(),
         );
     };
-    for date in range.clone() {
-        week.push(state.days.get(&date).map_or_else(
+    for date in date::days(range.clone()) {
+        if date.weekday() == Weekday::Mon && !week.is_empty() {
+            push_week(take(&mut week), week_start);
+            week_start = date;
+        }
+        let label = date::short_label(date);
+        week.push(state.model.days.get(&date).map_or_else(
             || {
                 container(row![
-                    text(format!("Day {date}")),
+                    text(label.clone()),
                     col![].width(Length::Fill),
                     button("+").on_press(Message::AddDay(date))
                 ])
@@ -679,22 +1636,19 @@ fn week_view<'a>(state: &State, range: &Range<Date>) -> Element<'a, Message> {
                 .into()
             },
             |day| {
-                container(
-                    button(text(format!(
-                        "Day {date}: {} meals addded",
-                        day.meals.len()
-                    )))
-                    .on_press(Message::ChangeToPage(Page::DayView(DayPage::new(date)))),
-                )
+                let totals = nutrition::day_totals(&state.model, date);
+                container(col![
+                    button(text(format!("{label}: {} meals addded", day.meals.len())))
+                        .on_press(Message::ChangeToPage(Page::DayView(DayPage::new(date)))),
+                    nutrition::summary_view(totals, &state.model.nutrition_targets),
+                ])
                 .style(theme::Container::Box)
                 .into()
             },
         ));
-        if date % 7 == 0 && !week.is_empty() {
-            push_week(take(&mut week), week_start);
-            week_start = date;
-            continue;
-        }
+    }
+    if !week.is_empty() {
+        push_week(week, week_start);
     }
 
     scrollable(col(weeks).spacing(10)).into()
@@ -717,17 +1671,14 @@ fn bar_view<'a>(state: &'a State) -> Row<'a, Message> {
         col![].width(Length::FillPortion(1)),
         on_press_and(
             header_button("Week", HeaderButtonStyle),
-            Message::ChangeToPage(Page::WeekView(1..100)),
+            Message::ChangeToPage(Page::WeekView(date::weeks_from_now(WEEKS_SHOWN))),
             !matches!(state.page, Page::WeekView(_))
         ),
         col![].width(Length::FillPortion(1)),
         on_press_and(
             header_button("List", HeaderButtonStyle),
-            Message::ChangeToPage(Page::ShoppingView {
-                from: 0,
-                until: 100
-            }),
-            !matches!(state.page, Page::ShoppingView { .. })
+            Message::ChangeToPage(Page::ShoppingView),
+            !matches!(state.page, Page::ShoppingView)
         ),
         col![].width(Length::FillPortion(1)),
         on_press_and(
@@ -736,7 +1687,59 @@ fn bar_view<'a>(state: &'a State) -> Row<'a, Message> {
             !matches!(state.page, Page::MealList)
         ),
         col![].width(Length::FillPortion(1)),
-        header_button("Calender", HeaderButtonStyle),
+        on_press_and(
+            header_button("Pantry", HeaderButtonStyle),
+            Message::ChangeToPage(Page::PantryView),
+            !matches!(state.page, Page::PantryView)
+        ),
+        col![].width(Length::FillPortion(1)),
+        on_press_and(
+            header_button("Calendar", HeaderButtonStyle),
+            Message::ChangeToPage(Page::CalendarView(CalendarPage::new(date::today()))),
+            !matches!(state.page, Page::CalendarView(_))
+        ),
     ]
     .width(Length::Fill)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picked(typed: &str) -> (Arc<str>, IngrediantQuantity) {
+        let mut state = State::default();
+        let meal_id = state.model.meals.push(Meal::new("Soup".into()));
+        state.page = Page::MealEditorView(MealEditorPage::new(meal_id));
+        let _ = on_ingrediant_picked_for_meal(&mut state, typed.into(), meal_id);
+
+        let meal = state.model.meals.get(meal_id).expect("the meal exists");
+        let (key, quantity) = meal
+            .ingrediants
+            .iter()
+            .next()
+            .expect("an ingrediant was added");
+        let ingrediant = state
+            .model
+            .ingrediants
+            .get(*key)
+            .expect("the ingrediant exists");
+        (ingrediant.name.clone(), quantity.clone())
+    }
+
+    #[test]
+    fn picking_reads_typed_lines() {
+        let (name, quantity) = picked("a pinch of salt");
+        assert_eq!(&*name, "salt");
+        assert_eq!(quantity.quantity, 1.0);
+        assert_eq!(quantity.unit, Unit::Solid(SolidUnit::Pinch));
+
+        let (name, quantity) = picked("half cup milk");
+        assert_eq!(&*name, "milk");
+        assert_eq!(quantity.quantity, 0.5);
+        assert_eq!(quantity.unit, Unit::Liquid(LiquidUnit::Cups));
+
+        let (name, quantity) = picked("Pound cake");
+        assert_eq!(&*name, "Pound cake");
+        assert_eq!(quantity.quantity, 0.0);
+    }
+}
@@ -2,6 +2,7 @@ use crate::{
     generational_map::GenerationalKey,
     ingrediant::{IngrediantKey, IngrediantQuantity},
     meal_editor::MealEditorPage,
+    stable_id::StableId,
     styles::{delete_button, edit_icon},
     Page,
 };
@@ -10,11 +11,38 @@ use std::{collections::BTreeMap, sync::Arc};
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Meal {
+    pub id: StableId,
     pub name: Arc<str>,
+    /// How many portions `ingrediants` makes
+    pub servings: u32,
+    #[serde(with = "crate::save_file::as_pairs")]
     pub ingrediants: BTreeMap<IngrediantKey, IngrediantQuantity>,
 }
 pub type MealKey = GenerationalKey<Meal>;
 
+impl Meal {
+    pub fn new(name: Arc<str>) -> Self {
+        Self {
+            id: StableId::new(),
+            name,
+            servings: 1,
+            ingrediants: BTreeMap::new(),
+        }
+    }
+
+    /// How much of the recipe is needed to serve `portions`
+    pub fn scale_for(&self, portions: u32) -> f64 {
+        f64::from(portions) / f64::from(self.servings.max(1))
+    }
+}
+
+/// A meal planned on a day, and how many people it is for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PlannedMeal {
+    pub meal_id: MealKey,
+    pub portions: u32,
+}
+
 use iced::{
     theme,
     widget::{button, row, text},
@@ -1,12 +1,13 @@
 use crate::{
     col,
     generational_map::GenerationalMap,
-    ingrediant::{Ingrediant, IngrediantKey, IngrediantQuantity},
-    meal,
+    ingrediant::{unit_choices, Ingrediant, IngrediantKey, IngrediantQuantity, UnitChoice},
+    ingrediant_editor::IngrediantEditorPage,
+    meal, nutrition,
     page::AnyPage,
     row,
     styles::delete_button,
-    IngrediantField, Message, State, UNITS,
+    IngrediantField, Message, Page, State,
 };
 
 use super::PickerState;
@@ -40,13 +41,20 @@ impl AnyPage for MealEditorPage {
         Command::none()
     }
 
+    fn picker_input(&mut self, input: String) -> Command<Message> {
+        if let Some(picker) = self.ingredaint_picker.as_mut() {
+            picker.input(input);
+        }
+        Command::none()
+    }
+
     fn close_popup(&mut self) -> Command<Message> {
         self.close_picker();
         Command::none()
     }
 
     fn open_picker(&mut self, state: &State) -> Command<Message> {
-        self.open_ingrediant_picker(&state.ingrediants)
+        self.open_ingrediant_picker(&state.model.ingrediants)
     }
 
     fn on_tab(&mut self, _: bool) -> Command<Message> {
@@ -56,7 +64,7 @@ impl AnyPage for MealEditorPage {
 }
 
 #[derive(Clone)]
-struct MessageConverter {
+pub struct MessageConverter {
     meal_id: MealKey,
 }
 
@@ -79,7 +87,7 @@ impl MealEditorPage {
     }
 
     pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
-        let Some(meal) = state.meals.get(self.meal_id) else {
+        let Some(meal) = state.model.meals.get(self.meal_id) else {
             return col![Element::<_>::from(text("Meal not found"))].into();
         };
         let rows = meal
@@ -91,17 +99,34 @@ impl MealEditorPage {
 
         let meal_title = text(
             state
+                .model
                 .meals
                 .get(self.meal_id)
                 .map_or("Unknown Meal", |meal| &meal.name),
         );
 
+        let meal_id = self.meal_id;
+        let servings = row![
+            text("Serves").width(Length::Fill),
+            iced_aw::number_input(meal.servings, 99, move |servings| {
+                Message::SetMealServings { meal_id, servings }
+            })
+            .min(1)
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
         let plus_button = button("Add").on_press(Message::AddMealIngrediant);
 
+        let per_portion = nutrition::meal_totals(&state.model, meal).scaled(meal.scale_for(1));
+
         let under_content = col![
             meal_title,
+            servings,
             col(rows).width(Length::Fill).spacing(10),
-            plus_button
+            plus_button,
+            text("Per portion").size(20),
+            nutrition::totals_view(per_portion, None),
         ]
         .spacing(10);
 
@@ -156,13 +181,16 @@ fn meal_editor_row<'a>(
         ingrediant_id,
     });
 
-    let ingredaint_name = text(
-        state
-            .ingrediants
-            .get(ingrediant_id)
+    let ingrediant = state.model.ingrediants.get(ingrediant_id);
+    let ingredaint_name = button(text(
+        ingrediant
             .map(|ing| (*ing.name).to_string())
             .unwrap_or("<Select>".to_string()),
-    )
+    ))
+    .style(theme::Button::Text)
+    .on_press(Message::ChangeToPage(Page::IngrediantEditorView(
+        IngrediantEditorPage::new(ingrediant_id, &state.model),
+    )))
     .width(Length::FillPortion(3));
 
     let quantity_feild = iced_aw::number_input(*quantity, 9999.0, move |input| {
@@ -174,10 +202,14 @@ fn meal_editor_row<'a>(
     })
     .width(Length::FillPortion(2));
 
-    let unit_select = pick_list(UNITS, Some(*unit), move |u| Message::UpdateMealIngrediant {
-        meal_id,
-        ingrediant_id,
-        field: IngrediantField::Unit(u),
+    let units = unit_choices(ingrediant, state.preferences.unit_system, *unit);
+    let selected = UnitChoice::new(ingrediant, *unit);
+    let unit_select = pick_list(units, Some(selected), move |u| {
+        Message::UpdateMealIngrediant {
+            meal_id,
+            ingrediant_id,
+            field: IngrediantField::Unit(u.unit),
+        }
     })
     .width(Length::Shrink);
     let inner = row![
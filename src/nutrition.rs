@@ -0,0 +1,425 @@
+//! Energy and nutrients of ingrediants, added up for meals and days and compared with daily
+//! targets.
+
+use std::{cmp::Ordering, fmt::Display, ops::Add};
+
+use iced::{
+    widget::{column as col, row, text},
+    Color, Element, Length,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ingrediant::{Ingrediant, IngrediantQuantity},
+    meal::Meal,
+    unit::{SolidUnit, SpoonRegion, Unit},
+    Date, Message, Model, State,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum Nutrient {
+    Energy,
+    Protein,
+    Fat,
+    Carbs,
+    Fibre,
+}
+
+impl Nutrient {
+    pub const ALL: [Self; 5] = [
+        Self::Energy,
+        Self::Protein,
+        Self::Fat,
+        Self::Carbs,
+        Self::Fibre,
+    ];
+
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Energy => "Energy",
+            Self::Protein => "Protein",
+            Self::Fat => "Fat",
+            Self::Carbs => "Carbs",
+            Self::Fibre => "Fibre",
+        }
+    }
+
+    pub const fn unit(self) -> &'static str {
+        match self {
+            Self::Energy => "kcal",
+            _ => "g",
+        }
+    }
+
+    /// `value` with its unit, e.g. "520 kcal" or "12.5 g"
+    pub fn label(self, value: f64) -> String {
+        match self {
+            Self::Energy => format!("{value:.0} {}", self.unit()),
+            _ => format!("{:.1} {}", (value * 10.).round() / 10., self.unit()),
+        }
+    }
+}
+
+impl Display for Nutrient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// An amount of each nutrient, energy in kcal and the rest in grams
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Nutrients {
+    pub energy: f64,
+    pub protein: f64,
+    pub fat: f64,
+    pub carbs: f64,
+    pub fibre: f64,
+}
+
+impl Nutrients {
+    pub const fn get(&self, nutrient: Nutrient) -> f64 {
+        match nutrient {
+            Nutrient::Energy => self.energy,
+            Nutrient::Protein => self.protein,
+            Nutrient::Fat => self.fat,
+            Nutrient::Carbs => self.carbs,
+            Nutrient::Fibre => self.fibre,
+        }
+    }
+
+    pub fn get_mut(&mut self, nutrient: Nutrient) -> &mut f64 {
+        match nutrient {
+            Nutrient::Energy => &mut self.energy,
+            Nutrient::Protein => &mut self.protein,
+            Nutrient::Fat => &mut self.fat,
+            Nutrient::Carbs => &mut self.carbs,
+            Nutrient::Fibre => &mut self.fibre,
+        }
+    }
+
+    pub fn scaled(mut self, by: f64) -> Self {
+        for nutrient in Nutrient::ALL {
+            *self.get_mut(nutrient) *= by;
+        }
+        self
+    }
+}
+
+impl Add for Nutrients {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self {
+        for nutrient in Nutrient::ALL {
+            *self.get_mut(nutrient) += other.get(nutrient);
+        }
+        self
+    }
+}
+
+/// The nutrients in an amount of an ingrediant
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Nutrition {
+    /// The amount `nutrients` are for, 100 g unless they are given per piece or per unit
+    pub per: IngrediantQuantity,
+    pub nutrients: Nutrients,
+}
+
+impl Default for Nutrition {
+    fn default() -> Self {
+        Self {
+            per: IngrediantQuantity {
+                quantity: 100.0,
+                unit: Unit::Solid(SolidUnit::Grams),
+            },
+            nutrients: Nutrients::default(),
+        }
+    }
+}
+
+/// The nutrients in `ammount` of `ingrediant`, if its nutrition is known and the amount can be
+/// converted to what it is given for
+pub fn of_ingrediant(
+    ingrediant: &Ingrediant,
+    ammount: &IngrediantQuantity,
+    spoons: SpoonRegion,
+) -> Option<Nutrients> {
+    let nutrition = ingrediant.nutrition.as_ref()?;
+    let (quantity, unit) = ingrediant.resolve(ammount.quantity, ammount.unit);
+    let (per, per_unit) = ingrediant.resolve(nutrition.per.quantity, nutrition.per.unit);
+    if per <= 0.0 {
+        return None;
+    }
+    let in_per_unit = ingrediant
+        .conversions(spoons)
+        .convert(quantity, unit, per_unit)?;
+    Some(nutrition.nutrients.scaled(in_per_unit / per))
+}
+
+/// Nutrients added up, along with how many ingrediants had to be left out
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Totals {
+    pub nutrients: Nutrients,
+    /// Ingrediants whose nutrition isn't known or whose amount couldn't be converted
+    pub missing: usize,
+}
+
+impl Totals {
+    pub fn scaled(self, by: f64) -> Self {
+        Self {
+            nutrients: self.nutrients.scaled(by),
+            ..self
+        }
+    }
+}
+
+impl Add for Totals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            nutrients: self.nutrients + other.nutrients,
+            missing: self.missing + other.missing,
+        }
+    }
+}
+
+/// Totals for all of `meal`, every serving it makes
+pub fn meal_totals(model: &Model, meal: &Meal) -> Totals {
+    let mut totals = Totals::default();
+    for (ingrediant_id, ammount) in &meal.ingrediants {
+        match model
+            .ingrediants
+            .get(*ingrediant_id)
+            .and_then(|ingrediant| of_ingrediant(ingrediant, ammount, model.spoon_region))
+        {
+            Some(nutrients) => totals.nutrients = totals.nutrients + nutrients,
+            None => totals.missing += 1,
+        }
+    }
+    totals
+}
+
+/// Totals of the meals planned on `date`, each scaled to the portions planned
+pub fn day_totals(model: &Model, date: Date) -> Totals {
+    let Some(day) = model.days.get(&date) else {
+        return Totals::default();
+    };
+    day.meals
+        .iter()
+        .filter_map(|planned| {
+            let meal = model.meals.get(planned.meal_id)?;
+            Some(meal_totals(model, meal).scaled(meal.scale_for(planned.portions)))
+        })
+        .fold(Totals::default(), Add::add)
+}
+
+/// How `total` compares with `target`, within a tenth of it counts as on target. `None` when
+/// there is no target.
+pub fn compare(total: f64, target: f64) -> Option<Ordering> {
+    if target <= 0.0 {
+        return None;
+    }
+    Some(if total < target * 0.9 {
+        Ordering::Less
+    } else if total > target * 1.1 {
+        Ordering::Greater
+    } else {
+        Ordering::Equal
+    })
+}
+
+/// The nutrients that are off target, e.g. "Energy over, Protein under"
+pub fn off_target(nutrients: &Nutrients, targets: &Nutrients) -> Vec<(Nutrient, Ordering)> {
+    Nutrient::ALL
+        .into_iter()
+        .filter_map(|nutrient| {
+            compare(nutrients.get(nutrient), targets.get(nutrient))
+                .filter(|ordering| ordering.is_ne())
+                .map(|ordering| (nutrient, ordering))
+        })
+        .collect()
+}
+
+fn ordering_color(ordering: Ordering) -> Color {
+    match ordering {
+        Ordering::Less => Color::from_rgb(0.9, 0.6, 0.1),
+        Ordering::Equal => Color::from_rgb(0.3, 0.7, 0.3),
+        Ordering::Greater => Color::from_rgb(0.9, 0.2, 0.2),
+    }
+}
+
+const fn ordering_label(ordering: Ordering) -> &'static str {
+    match ordering {
+        Ordering::Less => "under",
+        Ordering::Equal => "on target",
+        Ordering::Greater => "over",
+    }
+}
+
+/// A line per nutrient, with how it compares to `targets` when given
+pub fn totals_view<'a>(totals: Totals, targets: Option<&Nutrients>) -> Element<'a, Message> {
+    let lines = Nutrient::ALL.into_iter().map(|nutrient| {
+        let total = totals.nutrients.get(nutrient);
+        let mut line = row![
+            text(nutrient).width(Length::Fill),
+            text(nutrient.label(total)).width(Length::Fill),
+        ]
+        .spacing(5);
+        if let Some(target) = targets.map(|targets| targets.get(nutrient)) {
+            line = line.push(match compare(total, target) {
+                Some(ordering) => text(format!(
+                    "{} of {}",
+                    ordering_label(ordering),
+                    nutrient.label(target)
+                ))
+                .style(ordering_color(ordering))
+                .width(Length::Fill),
+                None => text("").width(Length::Fill),
+            });
+        }
+        line.into()
+    });
+    let missing = (totals.missing > 0).then(|| {
+        text(format!(
+            "{} ingrediant(s) left out, their nutrition isn't known",
+            totals.missing
+        ))
+        .size(12)
+    });
+    col(lines).push_maybe(missing).spacing(2).into()
+}
+
+/// A short summary for the week view, e.g. "1850 kcal, protein under"
+pub fn summary_view<'a>(totals: Totals, targets: &Nutrients) -> Element<'a, Message> {
+    let energy = text(Nutrient::Energy.label(totals.nutrients.energy)).size(12);
+    let flags = off_target(&totals.nutrients, targets)
+        .into_iter()
+        .map(|(nutrient, ordering)| {
+            text(format!(
+                "{} {}",
+                nutrient.to_string().to_lowercase(),
+                ordering_label(ordering)
+            ))
+            .size(12)
+            .style(ordering_color(ordering))
+            .into()
+        });
+    row(std::iter::once(energy.into()).chain(flags))
+        .spacing(10)
+        .into()
+}
+
+/// Inputs for the daily targets, 0 for no target
+pub fn targets_view(state: &State) -> Element<'_, Message> {
+    let targets = Nutrient::ALL.into_iter().map(|nutrient| {
+        row![
+            text(format!("{nutrient} ({})", nutrient.unit())).width(Length::Fill),
+            iced_aw::number_input(
+                state.model.nutrition_targets.get(nutrient),
+                99999.0,
+                move |target| Message::SetNutritionTarget(nutrient, target),
+            )
+            .min(0.0),
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center)
+        .into()
+    });
+    col![text("Daily targets").size(16), col(targets).spacing(5)]
+        .spacing(5)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        meal::PlannedMeal,
+        unit::{AmbiguosUnit, LiquidUnit},
+        Day,
+    };
+
+    fn nutrition(per: f64, unit: Unit, energy: f64, protein: f64) -> Option<Nutrition> {
+        Some(Nutrition {
+            per: IngrediantQuantity {
+                quantity: per,
+                unit,
+            },
+            nutrients: Nutrients {
+                energy,
+                protein,
+                ..Nutrients::default()
+            },
+        })
+    }
+
+    #[test]
+    fn converts_to_what_nutrition_is_given_for() {
+        let mut milk = Ingrediant::new("Milk".into());
+        milk.nutrition = nutrition(100.0, Unit::Solid(SolidUnit::Grams), 64.0, 3.4);
+        let glass = IngrediantQuantity {
+            quantity: 200.0,
+            unit: Unit::Liquid(LiquidUnit::MilliLiters),
+        };
+        // Volumes can't be weighed without a density
+        assert_eq!(of_ingrediant(&milk, &glass, SpoonRegion::Metric), None);
+
+        milk.density = Some(1.0);
+        let nutrients = of_ingrediant(&milk, &glass, SpoonRegion::Metric).unwrap();
+        assert_eq!(nutrients.energy, 128.0);
+        assert_eq!(nutrients.protein, 6.8);
+    }
+
+    #[test]
+    fn adds_up_days() {
+        let mut model = Model::default();
+        let mut egg = Ingrediant::new("Egg".into());
+        egg.nutrition = nutrition(1.0, Unit::Ambigous(AmbiguosUnit::Count), 70.0, 6.0);
+        let egg = model.ingrediants.push(egg);
+        let salt = model.ingrediants.push(Ingrediant::new("Salt".into()));
+
+        let mut omelette = Meal::new("Omelette".into());
+        omelette.servings = 2;
+        omelette.ingrediants.insert(
+            egg,
+            IngrediantQuantity {
+                quantity: 4.0,
+                unit: Unit::Ambigous(AmbiguosUnit::Count),
+            },
+        );
+        omelette.ingrediants.insert(
+            salt,
+            IngrediantQuantity {
+                quantity: 1.0,
+                unit: Unit::Solid(SolidUnit::Pinch),
+            },
+        );
+        let omelette = model.meals.push(omelette);
+        let date = Date::from_ymd_opt(2024, 10, 14).unwrap();
+        model.days.insert(
+            date,
+            Day {
+                date,
+                meals: vec![PlannedMeal {
+                    meal_id: omelette,
+                    portions: 3,
+                }],
+            },
+        );
+
+        let totals = day_totals(&model, date);
+        assert_eq!(totals.nutrients.energy, 420.0);
+        assert_eq!(totals.missing, 1);
+
+        let targets = Nutrients {
+            energy: 2000.0,
+            protein: 36.0,
+            ..Nutrients::default()
+        };
+        assert_eq!(
+            off_target(&totals.nutrients, &targets),
+            [(Nutrient::Energy, Ordering::Less)]
+        );
+    }
+}
@@ -3,32 +3,122 @@ use std::ops::Range;
 use iced::{widget, Command, Element};
 use serde::{Deserialize, Serialize};
 
-use crate::{day_page, meal_editor, Date, Message, State};
+use crate::{
+    calendar_page, date, day_page, ingrediant::IngrediantKey, ingrediant_editor, meal::MealKey,
+    meal_editor, recipe_import, Date, Message, Model, State, WEEKS_SHOWN,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Page {
     DayView(day_page::DayPage),
-    MealList(meal),
+    MealList,
     MealEditorView(meal_editor::MealEditorPage),
-    ShoppingView { from: Date, until: Date },
+    ShoppingView,
     WeekView(Range<Date>),
+    CalendarView(calendar_page::CalendarPage),
+    RecipeImportView(recipe_import::RecipeImportPage),
+    PantryView,
+    AislesView,
+    IngrediantEditorView(ingrediant_editor::IngrediantEditorPage),
 }
 
 impl Page {
-    fn as_any(&self) -> Box<dyn AnyPage> {
+    pub fn as_any_mut(&mut self) -> Option<&mut dyn AnyPage> {
         match self {
-            Page::DayView(dp) => dp,
-            Page::MealList => self,
-            Page::MealEditorView(_) => mev,
-            Page::ShoppingView { from, until } => todo!(),
-            Page::WeekView(_) => todo!(),
+            Page::CalendarView(page) => Some(page),
+            Page::DayView(page) => Some(page),
+            Page::MealEditorView(page) => Some(page),
+            Page::IngrediantEditorView(page) => Some(page),
+            Page::RecipeImportView(page) => Some(page),
+            Page::MealList
+            | Page::PantryView
+            | Page::AislesView
+            | Page::ShoppingView
+            | Page::WeekView(_) => None,
+        }
+    }
+
+    pub fn on_tab(&mut self, shift_down: bool) -> Command<Message> {
+        match self.as_any_mut() {
+            Some(page) => page.on_tab(shift_down),
+            None => focus_step(shift_down),
+        }
+    }
+
+    /// What is saved to open the page again
+    pub fn saved(&self) -> SavedPage {
+        match self {
+            Page::DayView(page) => SavedPage::DayView(page.date),
+            // The draft isn't kept, its import is started from the meal list
+            Page::MealList | Page::RecipeImportView(_) => SavedPage::MealList,
+            Page::MealEditorView(page) => SavedPage::MealEditorView(page.meal_id),
+            Page::ShoppingView => SavedPage::ShoppingView,
+            Page::WeekView(_) => SavedPage::WeekView,
+            Page::CalendarView(_) => SavedPage::CalendarView,
+            Page::PantryView => SavedPage::PantryView,
+            Page::AislesView => SavedPage::AislesView,
+            Page::IngrediantEditorView(page) => SavedPage::IngrediantEditorView(page.ingrediant_id),
+        }
+    }
+}
+
+/// The open page as it is saved, which page it is and what it shows. Input fields aren't kept, and
+/// the week and calendar open on the current week and month.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum SavedPage {
+    DayView(Date),
+    MealList,
+    MealEditorView(MealKey),
+    ShoppingView,
+    #[default]
+    WeekView,
+    CalendarView,
+    PantryView,
+    AislesView,
+    IngrediantEditorView(IngrediantKey),
+}
+
+impl SavedPage {
+    /// Opens the page again, the week view if what it showed is gone
+    pub fn restore(self, model: &Model) -> Page {
+        match self {
+            Self::DayView(date) => Page::DayView(day_page::DayPage::new(date)),
+            Self::MealList => Page::MealList,
+            Self::MealEditorView(meal_id) if model.meals.get(meal_id).is_some() => {
+                Page::MealEditorView(meal_editor::MealEditorPage::new(meal_id))
+            }
+            Self::ShoppingView => Page::ShoppingView,
+            Self::CalendarView => {
+                Page::CalendarView(calendar_page::CalendarPage::new(date::today()))
+            }
+            Self::PantryView => Page::PantryView,
+            Self::AislesView => Page::AislesView,
+            Self::IngrediantEditorView(ingrediant_id)
+                if model.ingrediants.get(ingrediant_id).is_some() =>
+            {
+                Page::IngrediantEditorView(ingrediant_editor::IngrediantEditorPage::new(
+                    ingrediant_id,
+                    model,
+                ))
+            }
+            Self::WeekView | Self::MealEditorView(_) | Self::IngrediantEditorView(_) => {
+                Page::default()
+            }
         }
     }
 }
 
 impl Default for Page {
     fn default() -> Self {
-        Self::WeekView(1..100)
+        Self::WeekView(date::weeks_from_now(WEEKS_SHOWN))
+    }
+}
+
+fn focus_step(shift_down: bool) -> Command<Message> {
+    if shift_down {
+        widget::focus_previous()
+    } else {
+        widget::focus_next()
     }
 }
 
@@ -36,17 +126,21 @@ pub trait AnyPage {
     fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message>;
 
     fn on_tab(&mut self, shift_down: bool) -> Command<Message> {
-        if shift_down {
-            widget::focus_previous()
-        } else {
-            widget::focus_next()
-        }
+        focus_step(shift_down)
     }
 
     fn vertical_movement(&mut self, _offset: isize) -> Command<Message> {
         Command::none()
     }
 
+    fn horizontal_movement(&mut self, _offset: isize) -> Command<Message> {
+        Command::none()
+    }
+
+    fn picker_input(&mut self, _input: String) -> Command<Message> {
+        Command::none()
+    }
+
     fn close_popup(&mut self) -> Command<Message> {
         Command::none()
     }
@@ -55,7 +149,10 @@ pub trait AnyPage {
         Command::none()
     }
 
-    fn on_focus() -> Command<Message> {
+    fn on_focus() -> Command<Message>
+    where
+        Self: Sized,
+    {
         Command::none()
     }
 }
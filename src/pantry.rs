@@ -0,0 +1,125 @@
+//! What is already at home. Pantry amounts are taken off the shopping list.
+
+use iced::{
+    theme,
+    widget::{button, column as col, container, pick_list, row, scrollable, text, text_input},
+    Element, Length,
+};
+
+use crate::{
+    ingrediant::{unit_choices, IngrediantKey, IngrediantQuantity, UnitChoice},
+    ingrediant_line,
+    styles::delete_button,
+    IngrediantField, Message, Model, State,
+};
+
+/// Adds a line like "2 kg rice" to the pantry, replacing the amount if the ingrediant is already
+/// there. Returns false if there was no ingrediant named.
+pub fn add_line(model: &mut Model, line: &str) -> bool {
+    let parsed = ingrediant_line::parse(line);
+    if parsed.name.is_empty() {
+        return false;
+    }
+    let key = model.ingrediant_named_or_add(parsed.name.into());
+    model.pantry.insert(
+        key,
+        IngrediantQuantity {
+            quantity: parsed.quantity,
+            unit: parsed.unit,
+        },
+    );
+    true
+}
+
+pub fn update(model: &mut Model, ingrediant_id: IngrediantKey, field: IngrediantField) {
+    if let Some(stock) = model.pantry.get_mut(&ingrediant_id) {
+        match field {
+            IngrediantField::Quantity(quantity) => stock.quantity = quantity,
+            IngrediantField::Unit(unit) => stock.unit = unit,
+        }
+    }
+}
+
+pub fn view(state: &State) -> Element<'_, Message> {
+    let rows = state
+        .model
+        .pantry
+        .iter()
+        .filter_map(|(ingrediant_id, stock)| pantry_row(state, *ingrediant_id, stock));
+
+    let adder = row![
+        text_input("e.g. 2 kg rice", &state.pantry_input)
+            .on_input(Message::SetPantryInput)
+            .on_submit(Message::AddPantryItem),
+        button("Add").on_press(Message::AddPantryItem),
+    ]
+    .spacing(10);
+
+    scrollable(col![text("Pantry").size(30), col(rows).spacing(10), adder].spacing(10)).into()
+}
+
+fn pantry_row<'a>(
+    state: &'a State,
+    ingrediant_id: IngrediantKey,
+    stock: &IngrediantQuantity,
+) -> Option<Element<'a, Message>> {
+    let ingrediant = state.model.ingrediants.get(ingrediant_id)?;
+
+    let quantity = iced_aw::number_input(stock.quantity, 99999.0, move |quantity| {
+        Message::UpdatePantryItem {
+            ingrediant_id,
+            field: IngrediantField::Quantity(quantity),
+        }
+    })
+    .width(Length::FillPortion(2));
+    let units = unit_choices(Some(ingrediant), state.preferences.unit_system, stock.unit);
+    let selected = UnitChoice::new(Some(ingrediant), stock.unit);
+    let unit = pick_list(units, Some(selected), move |choice| {
+        Message::UpdatePantryItem {
+            ingrediant_id,
+            field: IngrediantField::Unit(choice.unit),
+        }
+    })
+    .width(Length::Shrink);
+
+    let inner = row![
+        delete_button().on_press(Message::RemovePantryItem(ingrediant_id)),
+        text(&ingrediant.name).width(Length::FillPortion(3)),
+        quantity,
+        unit
+    ]
+    .align_items(iced::Alignment::Center)
+    .spacing(3)
+    .padding(2);
+    Some(container(inner).style(theme::Container::Box).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingrediant::Ingrediant,
+        unit::{SolidUnit, Unit},
+    };
+
+    #[test]
+    fn adds_typed_lines() {
+        let mut model = Model::default();
+        let rice = model.ingrediants.push(Ingrediant::new("Rice".into()));
+
+        assert!(add_line(&mut model, "2 kg rice"));
+        assert!(add_line(&mut model, "500g rice"));
+        assert!(add_line(&mut model, "Olive oil"));
+        assert!(!add_line(&mut model, "  "));
+
+        assert_eq!(model.ingrediants.len(), 2);
+        assert_eq!(
+            model.pantry.get(&rice),
+            Some(&IngrediantQuantity {
+                quantity: 500.0,
+                unit: Unit::Solid(SolidUnit::Grams),
+            })
+        );
+        assert_eq!(model.pantry.len(), 2);
+    }
+}
@@ -13,8 +13,16 @@ use std::{ops::Range, sync::Arc};
 
 use crate::Message;
 
+/// Most results shown at once, long lists are narrowed down by searching
+const SHOWN: usize = 50;
+
 pub trait NameToMessageConverter {
     fn convert(&self, name: Arc<str>) -> Message;
+
+    /// Whether a name that isn't listed can be picked, to create something called that
+    fn takes_new_names(&self) -> bool {
+        true
+    }
 }
 
 #[derive(Clone)]
@@ -29,50 +37,45 @@ pub struct PickerState<C: NameToMessageConverter> {
 
 impl<C: NameToMessageConverter> PickerState<C> {
     pub fn view(&self) -> Element<'_, Message> {
-        let results: Vec<Element<_>> = if self.search_results.is_empty() {
+        let results: Vec<(Element<_>, Arc<str>)> = if self.search_results.is_empty() {
             self.search_feilds
                 .iter()
+                .take(SHOWN)
                 .enumerate()
                 .map(|(i, name)| {
-                    if i == self.selection_index {
+                    let label = if i == self.selection_index {
                         text(format!("> {}", &name))
                     } else {
                         text(&name)
-                    }
-                    .into()
+                    };
+                    (label.into(), name.clone())
                 })
                 .collect()
         } else {
             self.search_results
                 .iter()
+                .take(SHOWN)
                 .enumerate()
                 .filter_map(|(i, result)| {
+                    let name = self.search_feilds.get(result.index)?.clone();
                     self.higlight_search_result(result, i)
-                        .map(|content|  content)
+                        .map(|content| (content.into(), name))
                 })
-                .map(| row| row.into())
                 .collect()
         };
 
-        let result_buttons = col(results.into_iter().zip(self.search_feilds.iter()).map(
-            |(ele,name)| button(ele).on_press(self.on_pick.convert(name.clone())).into()
+        let result_buttons = col(results.into_iter().map(
+            |(ele,name)| button(ele).on_press(self.on_pick.convert(name)).into()
         ));
 
-        let selected_result = 
-        // self
-            // .search_feilds
-            // .get(self.selection_index)
-            // .cloned()
-            // .unwrap_or_else(|| 
-                self.input_field.clone().into()
-                    // .into())
-        ;
-
         let input_feild = 
             text_input("Search", &self.input_field)
                 .on_input(Message::MealPickerInput)
-                .on_submit(self.on_pick.convert(selected_result))
                 .id( self.input_feild_id.clone() );
+        let input_feild = match self.submitted() {
+            Some(name) => input_feild.on_submit(self.on_pick.convert(name)),
+            None => input_feild,
+        };
 
         
         let content = col![
@@ -156,15 +159,43 @@ impl<C: NameToMessageConverter> PickerState<C> {
         };
     }
 
+    /// How many results are listed
+    fn shown(&self) -> usize {
+        if self.search_results.is_empty() {
+            self.search_feilds.len()
+        } else {
+            self.search_results.len()
+        }
+        .min(SHOWN)
+    }
+
+    /// The name of the highlighted result
+    fn highlighted(&self) -> Option<Arc<str>> {
+        let index = if self.search_results.is_empty() {
+            self.selection_index
+        } else {
+            self.search_results.get(self.selection_index)?.index
+        };
+        self.search_feilds.get(index).cloned()
+    }
+
+    /// What enter picks, the highlighted result. Pickers taking new names pick the typed text
+    /// instead while the top result is highlighted, unless the text is empty.
+    fn submitted(&self) -> Option<Arc<str>> {
+        if self.on_pick.takes_new_names()
+            && self.selection_index == 0
+            && !self.input_field.is_empty()
+        {
+            return Some(self.input_field.as_str().into());
+        }
+        self.highlighted()
+    }
+
     pub fn vertical_movement(&mut self, offset: isize) {
-        let new_index = self.selection_index.saturating_add_signed(offset).min(
-            if self.search_results.is_empty() {
-                self.search_feilds.len()
-            } else {
-                self.search_results.len()
-            }
-            .saturating_sub(1),
-        );
+        let new_index = self
+            .selection_index
+            .saturating_add_signed(offset)
+            .min(self.shown().saturating_sub(1));
 
         self.selection_index = new_index;
     }
@@ -202,3 +233,41 @@ impl From<SR> for SearchResult {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Listed;
+
+    impl NameToMessageConverter for Listed {
+        fn convert(&self, name: Arc<str>) -> Message {
+            Message::FoodPicked(name)
+        }
+
+        fn takes_new_names(&self) -> bool {
+            false
+        }
+    }
+
+    #[test]
+    fn moves_within_shown_results() {
+        let names = (0..SHOWN + 10).map(|i| format!("Food {i}").into()).collect();
+        let mut picker = PickerState::new(names, Listed);
+        picker.vertical_movement(100);
+        assert_eq!(picker.selection_index, SHOWN - 1);
+
+        picker.input("Food 7".to_owned());
+        picker.vertical_movement(100);
+        assert_eq!(picker.selection_index, picker.search_results.len().min(SHOWN) - 1);
+    }
+
+    #[test]
+    fn submits_highlighted_result() {
+        let names = vec!["Apple".into(), "Apricot".into()];
+        let mut picker = PickerState::new(names, Listed);
+        picker.input("apr".to_owned());
+        assert_eq!(picker.submitted(), picker.highlighted());
+        assert_eq!(picker.submitted().as_deref(), Some("Apricot"));
+    }
+}
@@ -0,0 +1,474 @@
+//! Imports recipes published as schema.org `Recipe` JSON-LD, either a saved web page or a bare
+//! JSON-LD file. The recipe is turned into a draft meal that is reviewed before it is added.
+
+use std::sync::Arc;
+
+use iced::{
+    theme,
+    widget::{button, column as col, container, pick_list, row, scrollable, text, text_input},
+    Element, Length,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    ingrediant::IngrediantQuantity,
+    ingrediant_line,
+    meal::{Meal, MealKey},
+    page::AnyPage,
+    styles::delete_button,
+    unit::{units_for, Conversions},
+    IngrediantField, Message, Model, State,
+};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecipeDraft {
+    pub name: String,
+    pub servings: u32,
+    pub lines: Vec<DraftLine>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DraftLine {
+    /// The line as written in the recipe
+    pub text: String,
+    pub name: Arc<str>,
+    pub quantity: IngrediantQuantity,
+}
+
+#[derive(Debug, Clone)]
+pub enum DraftEdit {
+    Name(String),
+    Servings(u32),
+    Line(usize, IngrediantField),
+    RemoveLine(usize),
+}
+
+impl RecipeDraft {
+    /// Reads the first recipe in `contents`, which can be JSON-LD or a web page that embeds it
+    pub fn extract(contents: &str) -> Result<Self, String> {
+        let documents = match serde_json::from_str::<Value>(contents) {
+            Ok(document) => vec![document],
+            Err(_) => json_ld_scripts(contents)
+                .filter_map(|script| serde_json::from_str(script).ok())
+                .collect(),
+        };
+        let recipe = documents
+            .iter()
+            .find_map(find_recipe)
+            .ok_or_else(|| "no schema.org Recipe found".to_owned())?;
+
+        let name = recipe
+            .get("name")
+            .and_then(Value::as_str)
+            .map_or_else(|| "Imported recipe".to_owned(), clean_text);
+        let servings = recipe.get("recipeYield").and_then(servings).unwrap_or(1);
+        let lines = recipe
+            .get("recipeIngredient")
+            .or_else(|| recipe.get("ingredients"))
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(Value::as_str)
+            .map(clean_text)
+            .filter(|line| !line.is_empty())
+            .map(DraftLine::parse)
+            .collect();
+
+        Ok(Self {
+            name,
+            servings,
+            lines,
+        })
+    }
+
+    pub fn edit(&mut self, edit: DraftEdit) {
+        match edit {
+            DraftEdit::Name(name) => self.name = name,
+            DraftEdit::Servings(servings) => self.servings = servings,
+            DraftEdit::Line(index, field) => {
+                if let Some(line) = self.lines.get_mut(index) {
+                    match field {
+                        IngrediantField::Quantity(quantity) => line.quantity.quantity = quantity,
+                        IngrediantField::Unit(unit) => line.quantity.unit = unit,
+                    }
+                }
+            }
+            DraftEdit::RemoveLine(index) => {
+                if index < self.lines.len() {
+                    self.lines.remove(index);
+                }
+            }
+        }
+    }
+
+    /// Adds up the lines per ingrediant. Recipes sometimes list an ingrediant twice, e.g. for a
+    /// sauce and a topping, later lines are converted into the unit of the first. Returns the
+    /// indices of the lines that can't be converted as the second value.
+    fn merged(&self, model: &Model) -> (Vec<(Arc<str>, IngrediantQuantity)>, Vec<usize>) {
+        let mut merged: Vec<(Arc<str>, IngrediantQuantity)> = Vec::new();
+        let mut unmergeable = Vec::new();
+        for (index, line) in self.lines.iter().enumerate() {
+            let Some((_, existing)) = merged
+                .iter_mut()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&line.name))
+            else {
+                merged.push((line.name.clone(), line.quantity.clone()));
+                continue;
+            };
+            let conversions = model
+                .ingrediant_named(&line.name)
+                .and_then(|key| model.ingrediants.get(key))
+                .map_or(
+                    Conversions {
+                        spoons: model.spoon_region,
+                        ..Conversions::default()
+                    },
+                    |ingrediant| ingrediant.conversions(model.spoon_region),
+                );
+            match conversions.convert(line.quantity.quantity, line.quantity.unit, existing.unit) {
+                Some(quantity) => existing.quantity += quantity,
+                None => unmergeable.push(index),
+            }
+        }
+        (merged, unmergeable)
+    }
+
+    /// Lines repeating an ingrediant in a unit that can't be added to its first line
+    pub fn unmergeable_lines(&self, model: &Model) -> Vec<usize> {
+        self.merged(model).1
+    }
+
+    /// Adds the draft to `model` as a new meal. Ingrediants are matched by name ignoring case, the
+    /// rest are added. Nothing is added while some lines can't be added up, their indices are
+    /// returned instead.
+    pub fn add_to(self, model: &mut Model) -> Result<MealKey, Vec<usize>> {
+        let (merged, unmergeable) = self.merged(model);
+        if !unmergeable.is_empty() {
+            return Err(unmergeable);
+        }
+        let mut meal = Meal::new(self.name.into());
+        meal.servings = self.servings.max(1);
+        for (name, quantity) in merged {
+            let key = model.ingrediant_named_or_add(name);
+            meal.ingrediants.insert(key, quantity);
+        }
+        Ok(model.meals.push(meal))
+    }
+}
+
+impl DraftLine {
+    fn parse(text: String) -> Self {
+        let parsed = ingrediant_line::parse(&text);
+        let name = if parsed.name.is_empty() {
+            text.as_str().into()
+        } else {
+            parsed.name.into()
+        };
+        Self {
+            text,
+            name,
+            quantity: IngrediantQuantity {
+                quantity: parsed.quantity,
+                unit: parsed.unit,
+            },
+        }
+    }
+}
+
+pub async fn read(path: String) -> Result<RecipeDraft, String> {
+    let contents = async_std::fs::read_to_string(&path)
+        .await
+        .map_err(|err| format!("couldn't read {path}: {err}"))?;
+    RecipeDraft::extract(&contents).map_err(|err| format!("couldn't import {path}: {err}"))
+}
+
+/// The contents of every `<script type="application/ld+json">` in a web page
+fn json_ld_scripts(html: &str) -> impl Iterator<Item = &str> {
+    // ASCII lowercasing keeps byte offsets the same
+    let lower = html.to_ascii_lowercase();
+    let mut scripts = Vec::new();
+    let mut rest = 0;
+    while let Some(found) = lower[rest..].find("application/ld+json") {
+        let tag = rest + found;
+        let Some(start) = lower[tag..].find('>').map(|end| tag + end + 1) else {
+            break;
+        };
+        let Some(end) = lower[start..].find("</script").map(|end| start + end) else {
+            break;
+        };
+        scripts.push(&html[start..end]);
+        rest = end;
+    }
+    scripts.into_iter()
+}
+
+/// Searches a JSON-LD document for an object with the `Recipe` type, they are often inside an
+/// `@graph` or an array
+fn find_recipe(value: &Value) -> Option<&Value> {
+    match value {
+        Value::Object(object) => {
+            let is_recipe = match object.get("@type") {
+                Some(Value::String(kind)) => kind.ends_with("Recipe"),
+                Some(Value::Array(kinds)) => kinds
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .any(|kind| kind.ends_with("Recipe")),
+                _ => false,
+            };
+            if is_recipe {
+                Some(value)
+            } else {
+                object.values().find_map(find_recipe)
+            }
+        }
+        Value::Array(values) => values.iter().find_map(find_recipe),
+        _ => None,
+    }
+}
+
+/// `recipeYield` can be a number, text like "Serves 4" or a list of either
+fn servings(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(number) => number.as_u64().and_then(|n| u32::try_from(n).ok()),
+        Value::String(yield_text) => yield_text
+            .split(|c: char| !c.is_ascii_digit())
+            .find(|digits| !digits.is_empty())
+            .and_then(|digits| digits.parse().ok()),
+        Value::Array(values) => values.iter().find_map(servings),
+        _ => None,
+    }
+    .filter(|servings| *servings > 0)
+}
+
+/// Removes markup and the common html entities that end up in JSON-LD text
+fn clean_text(text: &str) -> String {
+    let mut cleaned = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => cleaned.push(c),
+            _ => {}
+        }
+    }
+    let cleaned = [
+        ("&nbsp;", " "),
+        ("&quot;", "\""),
+        ("&#39;", "'"),
+        ("&#039;", "'"),
+        ("&apos;", "'"),
+        ("&frac14;", "¼"),
+        ("&frac12;", "½"),
+        ("&frac34;", "¾"),
+        ("&amp;", "&"),
+    ]
+    .iter()
+    .fold(cleaned, |text, (entity, replacement)| {
+        text.replace(entity, replacement)
+    });
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Shows an imported recipe so it can be corrected before it is added
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeImportPage {
+    pub draft: RecipeDraft,
+}
+
+impl AnyPage for RecipeImportPage {
+    fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        self.view(state)
+    }
+}
+
+impl RecipeImportPage {
+    pub fn new(draft: RecipeDraft) -> Self {
+        Self { draft }
+    }
+
+    pub fn view<'a>(&'a self, state: &'a State) -> Element<'a, Message> {
+        let name = text_input("Meal name", &self.draft.name)
+            .on_input(|name| Message::EditRecipeDraft(DraftEdit::Name(name)));
+        let servings = row![
+            text("Serves").width(Length::Fill),
+            iced_aw::number_input(self.draft.servings, 99, |servings| {
+                Message::EditRecipeDraft(DraftEdit::Servings(servings))
+            })
+            .min(1)
+        ]
+        .spacing(5)
+        .align_items(iced::Alignment::Center);
+
+        let unmergeable = self.draft.unmergeable_lines(&state.model);
+        let lines = self
+            .draft
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(index, line)| draft_row(state, index, line, unmergeable.contains(&index)));
+
+        let actions = row![
+            button("Add meal").on_press_maybe(
+                unmergeable
+                    .is_empty()
+                    .then_some(Message::SaveImportedRecipe)
+            ),
+            button("Cancel").on_press(Message::BackPage),
+        ]
+        .spacing(10);
+
+        scrollable(
+            col![
+                text("Review recipe").size(30),
+                name,
+                servings,
+                col(lines).spacing(10),
+                actions
+            ]
+            .spacing(10),
+        )
+        .into()
+    }
+}
+
+fn draft_row<'a>(
+    state: &State,
+    index: usize,
+    line: &'a DraftLine,
+    unmergeable: bool,
+) -> Element<'a, Message> {
+    let name = if state.model.ingrediant_named(&line.name).is_some() {
+        text(&*line.name)
+    } else {
+        text(format!("{} (new)", line.name))
+    };
+
+    let quantity = iced_aw::number_input(line.quantity.quantity, 9999.0, move |quantity| {
+        Message::EditRecipeDraft(DraftEdit::Line(index, IngrediantField::Quantity(quantity)))
+    })
+    .width(Length::FillPortion(2));
+    let units = units_for(state.preferences.unit_system, line.quantity.unit);
+    let unit = pick_list(units, Some(line.quantity.unit), move |unit| {
+        Message::EditRecipeDraft(DraftEdit::Line(index, IngrediantField::Unit(unit)))
+    })
+    .width(Length::Shrink);
+
+    let parsed = row![
+        delete_button().on_press(Message::EditRecipeDraft(DraftEdit::RemoveLine(index))),
+        name.width(Length::FillPortion(3)),
+        quantity,
+        unit
+    ]
+    .align_items(iced::Alignment::Center)
+    .spacing(3);
+
+    let problem = unmergeable.then(|| {
+        text(format!(
+            "Can't be added to the earlier {} line, change the unit or remove a line",
+            line.name
+        ))
+        .size(12)
+    });
+
+    container(
+        col![parsed, text(&line.text).size(12)]
+            .push_maybe(problem)
+            .spacing(2),
+    )
+    .style(theme::Container::Box)
+    .padding(2)
+    .width(Length::Fill)
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingrediant::Ingrediant;
+    use crate::unit::{AmbiguosUnit, LiquidUnit, SolidUnit, Unit};
+
+    const PAGE: &str = r#"<html><head>
+        <script type="application/ld+json">{"@type": "WebSite", "name": "Recipes"}</script>
+        <script type='application/ld+json' class="yoast">
+        {"@context": "https://schema.org", "@graph": [
+            {"@type": "WebPage"},
+            {"@type": ["Recipe"], "name": "Mac &amp; cheese", "recipeYield": ["4", "4 servings"],
+             "recipeIngredient": ["250g macaroni", "2 tbsp <b>butter</b>", "&frac12; tsp salt", " "]}
+        ]}
+        </script></head><body></body></html>"#;
+
+    #[test]
+    fn reads_recipe_from_page() {
+        let draft = RecipeDraft::extract(PAGE).unwrap();
+        assert_eq!(draft.name, "Mac & cheese");
+        assert_eq!(draft.servings, 4);
+        let lines: Vec<_> = draft
+            .lines
+            .iter()
+            .map(|line| (&*line.name, line.quantity.quantity, line.quantity.unit))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![
+                ("macaroni", 250.0, Unit::Solid(SolidUnit::Grams)),
+                ("butter", 2.0, Unit::Ambigous(AmbiguosUnit::TableSpoon)),
+                ("salt", 0.5, Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_bare_json_ld() {
+        let json = r#"{"@type": "Recipe", "name": "Toast", "recipeYield": "Serves 2",
+            "recipeIngredient": ["2 slices bread"]}"#;
+        let draft = RecipeDraft::extract(json).unwrap();
+        assert_eq!(draft.servings, 2);
+        assert_eq!(draft.lines.len(), 1);
+        assert!(RecipeDraft::extract(r#"{"@type": "Person"}"#).is_err());
+    }
+
+    #[test]
+    fn adds_meal_matching_ingrediants() {
+        let mut model = Model::default();
+        let salt = model.ingrediants.push(Ingrediant::new("Salt".into()));
+        let key = RecipeDraft::extract(PAGE)
+            .unwrap()
+            .add_to(&mut model)
+            .unwrap();
+
+        let meal = model.meals.get(key).unwrap();
+        assert_eq!(meal.servings, 4);
+        assert_eq!(meal.ingrediants.len(), 3);
+        assert!(meal.ingrediants.contains_key(&salt));
+        assert_eq!(model.ingrediants.len(), 3);
+    }
+
+    #[test]
+    fn adds_up_repeated_ingrediants() {
+        let json = r#"{"@type": "Recipe", "name": "Pancakes",
+            "recipeIngredient": ["1 cup flour", "2 tbsp Flour", "2 eggs"]}"#;
+        let mut model = Model::default();
+        let key = RecipeDraft::extract(json)
+            .unwrap()
+            .add_to(&mut model)
+            .unwrap();
+        let meal = model.meals.get(key).unwrap();
+        assert_eq!(meal.ingrediants.len(), 2);
+        let flour = model.ingrediant_named("flour").unwrap();
+        let cups = &meal.ingrediants[&flour];
+        assert_eq!(cups.unit, Unit::Liquid(LiquidUnit::Cups));
+        assert!((cups.quantity - (1.0 + 30.0 / LiquidUnit::Cups.in_milliliters())).abs() < 1e-9);
+
+        // Without a density cups and grams can't be added up, so both lines are kept
+        let json = r#"{"@type": "Recipe", "name": "Bread",
+            "recipeIngredient": ["1 cup flour", "500 g flour"]}"#;
+        let mut model = Model::default();
+        let draft = RecipeDraft::extract(json).unwrap();
+        assert_eq!(draft.clone().add_to(&mut model), Err(vec![1]));
+        assert_eq!(model.meals.len(), 0);
+        assert_eq!(model.ingrediants.len(), 0);
+        assert_eq!(draft.lines.len(), 2);
+    }
+}
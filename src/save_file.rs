@@ -0,0 +1,534 @@
+//! On disk format of the app state.
+//!
+//! Files are a JSON envelope naming the format and its version around the state itself. When the
+//! layout of the state changes `VERSION` is bumped and a migration from the previous version is
+//! added to `MIGRATIONS`, so older files are upgraded step by step when loaded.
+//!
+//! Saves are written to a temporary file which is then renamed over the save file, so a crash part
+//! way through leaves the previous save intact. Timestamped backups are kept next to the save file.
+
+use std::{collections::BTreeMap, fmt::Display, io, path::Path};
+
+use async_std::{fs, io::WriteExt, stream::StreamExt};
+use chrono::{NaiveDateTime, TimeDelta};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    date, legacy, page::SavedPage, shopping::ShoppingRange, stable_id::StableId, Date, Model,
+    Preferences,
+};
+
+const BACKUP_SUFFIX: &str = ".backup-";
+const BACKUP_TIME_FORMAT: &str = "%Y%m%d-%H%M%S";
+/// How long to wait after a backup before making another
+const BACKUP_INTERVAL: TimeDelta = TimeDelta::hours(1);
+const DEFAULT_BACKUPS: usize = 5;
+
+const FORMAT: &str = "graze";
+pub const VERSION: u32 = 5;
+
+/// `MIGRATIONS[n]` upgrades a version `n + 1` file to version `n + 2`
+const MIGRATIONS: &[fn(Value) -> Value] = &[
+    split_view_state,
+    add_stable_ids,
+    shopping_page_without_dates,
+    save_page_descriptor,
+];
+
+/// What is written to the save file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub model: Model,
+    /// The page that was open, so the app can be reopened where it was left
+    pub page: SavedPage,
+    pub shopping_range: ShoppingRange,
+    pub preferences: Preferences,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    format: String,
+    version: u32,
+    state: T,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoadError {
+    /// Written by a newer version of the app
+    NewerVersion(u32),
+    Corrupt(String),
+    Io(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadFailure {
+    pub error: LoadError,
+    /// Where the unreadable file was copied to
+    pub copy: Option<String>,
+    /// Backups that could be restored instead, newest first
+    pub backups: Vec<String>,
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NewerVersion(version) => write!(
+                f,
+                "the save file is version {version}, this version of Graze only reads up to {VERSION}"
+            ),
+            Self::Corrupt(reason) => write!(f, "the save file could not be read: {reason}"),
+            Self::Io(reason) => write!(f, "the save file could not be opened: {reason}"),
+        }
+    }
+}
+
+pub fn encode(state: &SaveData) -> serde_json::Result<Vec<u8>> {
+    serde_json::to_vec(&Envelope {
+        format: FORMAT.to_owned(),
+        version: VERSION,
+        state,
+    })
+}
+
+/// Reads a save file. Files from before days were dated have day 1 put on `legacy_start`.
+pub fn decode(bytes: &[u8], legacy_start: Date) -> Result<SaveData, LoadError> {
+    let Ok(envelope) = serde_json::from_slice::<Envelope<Value>>(bytes) else {
+        return decode_legacy(bytes, legacy_start);
+    };
+    if envelope.format != FORMAT {
+        return Err(LoadError::Corrupt(format!(
+            "unknown format \"{}\"",
+            envelope.format
+        )));
+    }
+    migrate(envelope.state, envelope.version)
+}
+
+/// Files from before the versioned format were bare bincode
+fn decode_legacy(bytes: &[u8], start: Date) -> Result<SaveData, LoadError> {
+    let legacy: legacy::State = bincode::deserialize(bytes)
+        .map_err(|_| LoadError::Corrupt("not a Graze save file".to_owned()))?;
+    migrate(legacy.migrate(start), 1)
+}
+
+/// Where day 1 of an undated save file at `path` goes, the Monday of the week it was last written
+/// in as that is when its plan was made. The same file always gets the same dates, whenever it is
+/// first opened.
+pub async fn legacy_start(path: &str) -> Date {
+    let written = fs::metadata(path)
+        .await
+        .and_then(|metadata| metadata.modified())
+        .map_or_else(
+            |_| date::today(),
+            |modified| chrono::DateTime::<chrono::Local>::from(modified).date_naive(),
+        );
+    date::week_start(written)
+}
+
+fn migrate(mut state: Value, version: u32) -> Result<SaveData, LoadError> {
+    if version > VERSION {
+        return Err(LoadError::NewerVersion(version));
+    }
+    if version == 0 {
+        return Err(LoadError::Corrupt("invalid version 0".to_owned()));
+    }
+    for migration in &MIGRATIONS[version as usize - 1..] {
+        state = migration(state);
+    }
+    serde_json::from_value(state).map_err(|err| LoadError::Corrupt(err.to_string()))
+}
+
+/// Version 2 only saves the user's data and the open page, not navigation history or input fields
+fn split_view_state(state: Value) -> Value {
+    let Value::Object(mut fields) = state else {
+        return state;
+    };
+    let model: serde_json::Map<String, Value> = ["days", "meals", "ingrediants"]
+        .into_iter()
+        .filter_map(|field| Some((field.to_owned(), fields.remove(field)?)))
+        .collect();
+    let mut split = serde_json::Map::new();
+    split.insert("model".to_owned(), Value::Object(model));
+    if let Some(page) = fields.remove("page") {
+        split.insert("page".to_owned(), page);
+    }
+    Value::Object(split)
+}
+
+/// Version 3 gives every meal and ingrediant a `StableId`
+fn add_stable_ids(mut state: Value) -> Value {
+    for map in ["meals", "ingrediants"] {
+        let Some(Value::Array(slots)) = state.pointer_mut(&format!("/model/{map}/data")) else {
+            continue;
+        };
+        for slot in slots {
+            if let Some(Value::Object(item)) = slot.get_mut(1) {
+                item.entry("id")
+                    .or_insert_with(|| Value::String(StableId::new().to_string()));
+            }
+        }
+    }
+    state
+}
+
+/// Version 4 keeps the shopping list's dates in `shopping_range` rather than in its page
+fn shopping_page_without_dates(mut state: Value) -> Value {
+    if let Some(page) = state.get_mut("page") {
+        if page.get("ShoppingView").is_some() {
+            *page = Value::String("ShoppingView".to_owned());
+        }
+    }
+    state
+}
+
+/// Version 5 saves which page was open rather than the whole page, leaving out its input fields
+/// and drafts. Pages that can't be described are dropped, so the week view opens.
+fn save_page_descriptor(mut state: Value) -> Value {
+    let Some(fields) = state.as_object_mut() else {
+        return state;
+    };
+    let page = match fields.remove("page") {
+        Some(Value::String(kind)) => Value::String(kind),
+        Some(Value::Object(page)) => match page.into_iter().next() {
+            Some((kind, details)) => match kind.as_str() {
+                "DayView" => serde_json::json!({ kind: details["date"] }),
+                "MealEditorView" => serde_json::json!({ kind: details["meal_id"] }),
+                "IngrediantEditorView" => serde_json::json!({ kind: details["ingrediant_id"] }),
+                "RecipeImportView" => Value::String("MealList".to_owned()),
+                _ => Value::String(kind),
+            },
+            None => return state,
+        },
+        _ => return state,
+    };
+    fields.insert("page".to_owned(), page);
+    state
+}
+
+/// How many backups to keep, set with `GRAZE_BACKUPS`
+pub fn backup_count() -> usize {
+    std::env::var("GRAZE_BACKUPS")
+        .ok()
+        .and_then(|count| count.parse().ok())
+        .unwrap_or(DEFAULT_BACKUPS)
+}
+
+/// Replaces the file at `path` with `bytes`, backing up the old contents if the last backup is
+/// older than `BACKUP_INTERVAL` and keeping at most `backups` of them
+pub async fn write(path: &str, bytes: &[u8], backups: usize, now: NaiveDateTime) -> io::Result<()> {
+    if let Some(dir) = Path::new(path).parent() {
+        fs::create_dir_all(dir).await?;
+    }
+    let temp = format!("{path}.tmp");
+    let mut file = fs::File::create(&temp).await?;
+    file.write_all(bytes).await?;
+    file.sync_all().await?;
+    drop(file);
+
+    if backups > 0 && fs::metadata(path).await.is_ok() {
+        back_up(path, backups, now).await?;
+    }
+    fs::rename(&temp, path).await
+}
+
+async fn back_up(path: &str, keep: usize, now: NaiveDateTime) -> io::Result<()> {
+    let mut existing = list_backups(path).await;
+    let due = existing
+        .first()
+        .and_then(|newest| backup_time(newest))
+        .is_none_or(|made| now - made >= BACKUP_INTERVAL);
+    if due {
+        let backup = format!("{path}{BACKUP_SUFFIX}{}", now.format(BACKUP_TIME_FORMAT));
+        fs::copy(path, &backup).await?;
+        existing.insert(0, backup);
+    }
+    for old in existing.iter().skip(keep) {
+        fs::remove_file(old).await?;
+    }
+    Ok(())
+}
+
+/// Backups of the save file at `path`, newest first
+pub async fn list_backups(path: &str) -> Vec<String> {
+    let path = Path::new(path);
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let Some(prefix) = path
+        .file_name()
+        .map(|name| format!("{}{BACKUP_SUFFIX}", name.to_string_lossy()))
+    else {
+        return Vec::new();
+    };
+
+    let Ok(mut entries) = fs::read_dir(dir).await else {
+        return Vec::new();
+    };
+    let mut backups = Vec::new();
+    while let Some(entry) = entries.next().await {
+        let Ok(entry) = entry else {
+            continue;
+        };
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            backups.push(dir.join(entry.file_name()).to_string_lossy().into_owned());
+        }
+    }
+    backups.sort_unstable_by(|a, b| b.cmp(a));
+    backups
+}
+
+fn backup_time(backup: &str) -> Option<NaiveDateTime> {
+    let stamp = backup.rsplit_once(BACKUP_SUFFIX)?.1;
+    NaiveDateTime::parse_from_str(stamp, BACKUP_TIME_FORMAT).ok()
+}
+
+/// Serializes maps as a list of pairs, for maps whose keys aren't strings
+pub mod as_pairs {
+    use super::*;
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Vec::<(K, V)>::deserialize(deserializer).map(|pairs| pairs.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ingrediant::{Ingrediant, IngrediantQuantity},
+        meal::{Meal, PlannedMeal},
+        page::Page,
+        unit::{SolidUnit, Unit},
+        Day, WEEKS_SHOWN,
+    };
+
+    fn sample() -> SaveData {
+        let mut state = Model::default();
+        let flour = state.ingrediants.push(Ingrediant::new("Flour".into()));
+        let mut bread = Meal::new("Bread".into());
+        bread.ingrediants.insert(
+            flour,
+            IngrediantQuantity {
+                quantity: 500.0,
+                unit: Unit::Solid(SolidUnit::Grams),
+            },
+        );
+        let bread = state.meals.push(bread);
+        let date = date::today();
+        state.days.insert(
+            date,
+            Day {
+                date,
+                meals: vec![PlannedMeal {
+                    meal_id: bread,
+                    portions: 2,
+                }],
+            },
+        );
+        SaveData {
+            model: state,
+            page: SavedPage::MealList,
+            shopping_range: ShoppingRange::ThisWeek,
+            preferences: Preferences::default(),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let state = sample();
+        let decoded = decode(&encode(&state).unwrap(), date::today()).unwrap();
+        assert_eq!(decoded.model.meals, state.model.meals);
+        assert_eq!(decoded.model.ingrediants, state.model.ingrediants);
+        assert_eq!(decoded.model.days, state.model.days);
+        assert_eq!(decoded.page, SavedPage::MealList);
+        assert_eq!(decoded.shopping_range, ShoppingRange::ThisWeek);
+    }
+
+    #[test]
+    fn migrates_shopping_page() {
+        let state = serde_json::json!({
+            "model": {},
+            "page": {"ShoppingView": {"from": "2024-10-14", "until": "2025-01-20"}},
+        });
+        let bytes = serde_json::to_vec(&Envelope {
+            format: FORMAT.to_owned(),
+            version: 3,
+            state,
+        })
+        .unwrap();
+        let decoded = decode(&bytes, date::today()).unwrap();
+        assert_eq!(decoded.page, SavedPage::ShoppingView);
+        assert_eq!(decoded.shopping_range, ShoppingRange::default());
+    }
+
+    #[test]
+    fn saves_which_page_is_open() {
+        let mut model = Model::default();
+        let salt = model.ingrediants.push(Ingrediant::new("Salt".into()));
+        let page = |page: Value| {
+            let bytes = serde_json::to_vec(&Envelope {
+                format: FORMAT.to_owned(),
+                version: 4,
+                state: serde_json::json!({ "model": {}, "page": page }),
+            })
+            .unwrap();
+            decode(&bytes, date::today()).unwrap().page
+        };
+        let date = Date::from_ymd_opt(2024, 3, 6).unwrap();
+        assert_eq!(
+            page(serde_json::json!({ "DayView": { "date": date } })),
+            SavedPage::DayView(date)
+        );
+        assert_eq!(
+            page(serde_json::json!({ "WeekView": { "start": date, "end": date } })),
+            SavedPage::WeekView
+        );
+        assert_eq!(
+            page(serde_json::json!({ "RecipeImportView": { "draft": {} } })),
+            SavedPage::MealList
+        );
+        assert_eq!(page(serde_json::json!("PantryView")), SavedPage::PantryView);
+        let editor = serde_json::json!({ "IngrediantEditorView": {
+            "ingrediant_id": salt,
+            "density_input": "1.",
+            "unit_input": "pin",
+        } });
+        assert_eq!(page(editor), SavedPage::IngrediantEditorView(salt));
+
+        // The week view opens on the current week, and pages of removed things aren't reopened
+        assert!(matches!(
+            SavedPage::WeekView.restore(&model),
+            Page::WeekView(range) if range == date::weeks_from_now(WEEKS_SHOWN)
+        ));
+        assert!(matches!(
+            SavedPage::IngrediantEditorView(salt).restore(&model),
+            Page::IngrediantEditorView(_)
+        ));
+        model.ingrediants.remove(salt);
+        assert!(matches!(
+            SavedPage::IngrediantEditorView(salt).restore(&model),
+            Page::WeekView(_)
+        ));
+    }
+
+    #[test]
+    fn refuses_newer_versions() {
+        let bytes = serde_json::to_vec(&Envelope {
+            format: FORMAT.to_owned(),
+            version: VERSION + 1,
+            state: Value::Null,
+        })
+        .unwrap();
+        assert_eq!(
+            decode(&bytes, date::today()).err(),
+            Some(LoadError::NewerVersion(VERSION + 1))
+        );
+    }
+
+    #[test]
+    fn refuses_garbage() {
+        assert!(matches!(
+            decode(b"definitely not a save file", date::today()),
+            Err(LoadError::Corrupt(_))
+        ));
+    }
+
+    #[test]
+    fn reads_unversioned_files() {
+        // Bincode of the layout described in `legacy`, structs are written as tuples of their
+        // fields and enums as their variant index followed by the fields
+        let unit = Unit::Solid(SolidUnit::KiloGrams);
+        let key = (0u64, 0u64);
+        let page = (4u32, (1u64, 100u64));
+        let stack: Vec<()> = vec![];
+        let days = BTreeMap::from([(3u64, (3u64, vec![key, key]))]);
+        let meals = (
+            vec![(
+                0u64,
+                Some(("Bread", BTreeMap::from([(key, (0.5f64, unit))]))),
+            )],
+            Vec::<u64>::new(),
+        );
+        let ingrediants = (vec![(0u64, Some("Flour"))], Vec::<u64>::new());
+        let bytes = bincode::serialize(&(page, stack, days, meals, ingrediants, "", (true, false)))
+            .unwrap();
+
+        let start = Date::from_ymd_opt(2024, 3, 4).unwrap();
+        let state = decode(&bytes, start).unwrap().model;
+        let (meal_id, meal) = state.meals.iter().next().unwrap();
+        assert_ne!(meal.id, state.ingrediants.values().next().unwrap().id);
+        assert_eq!(&*meal.name, "Bread");
+        assert_eq!(meal.servings, 1);
+        let (ingrediant_id, ingrediant) = state.ingrediants.iter().next().unwrap();
+        assert_eq!(&*ingrediant.name, "Flour");
+        assert_eq!(
+            meal.ingrediants.get(&ingrediant_id),
+            Some(&IngrediantQuantity {
+                quantity: 0.5,
+                unit
+            })
+        );
+
+        let date = Date::from_ymd_opt(2024, 3, 6).unwrap();
+        let planned = PlannedMeal {
+            meal_id,
+            portions: 1,
+        };
+        assert_eq!(
+            state.days,
+            BTreeMap::from([(
+                date,
+                Day {
+                    date,
+                    meals: vec![planned, planned]
+                }
+            )])
+        );
+    }
+
+    #[test]
+    fn keeps_rolling_backups() {
+        let dir = std::env::temp_dir().join(format!("graze-backups-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data").to_string_lossy().into_owned();
+        let at = |hour| date::today().and_hms_opt(hour, 0, 0).unwrap();
+
+        async_std::task::block_on(async {
+            write(&path, b"1", 2, at(0)).await.unwrap();
+            assert!(list_backups(&path).await.is_empty());
+            write(&path, b"2", 2, at(1)).await.unwrap();
+            // Too soon after the last backup
+            write(&path, b"3", 2, at(1)).await.unwrap();
+            write(&path, b"4", 2, at(3)).await.unwrap();
+            write(&path, b"5", 2, at(5)).await.unwrap();
+
+            let backups = list_backups(&path).await;
+            let contents: Vec<Vec<u8>> = backups
+                .iter()
+                .map(|backup| std::fs::read(backup).unwrap())
+                .collect();
+            assert_eq!(contents, vec![b"4".to_vec(), b"3".to_vec()]);
+            assert_eq!(std::fs::read(&path).unwrap(), b"5");
+        });
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,761 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    mem::take,
+    ops::Range,
+    sync::Arc,
+};
+
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aisles, date,
+    ingrediant::{IngrediantKey, IngrediantQuantity, UnitChoice},
+    meal::MealKey,
+    unit::{apropriate_unit, merge_into_unit, Conversions, Unit, UnitSystem},
+    Date, Model,
+};
+
+/// Total needed of each ingrediant, split by base unit when units can't be merged
+pub type ShoppingList = BTreeMap<IngrediantKey, BTreeMap<Unit, f64>>;
+
+/// Which days the shopping list is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShoppingRange {
+    ThisWeek,
+    NextDays(u32),
+    /// `from` to `until`, both included
+    Custom {
+        from: Date,
+        until: Date,
+    },
+}
+
+impl Default for ShoppingRange {
+    fn default() -> Self {
+        Self::NextDays(7)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeKind {
+    ThisWeek,
+    NextDays,
+    Custom,
+}
+
+impl RangeKind {
+    pub const ALL: [Self; 3] = [Self::ThisWeek, Self::NextDays, Self::Custom];
+}
+
+impl Display for RangeKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::ThisWeek => "This week",
+            Self::NextDays => "Next days",
+            Self::Custom => "Custom",
+        })
+    }
+}
+
+impl ShoppingRange {
+    pub fn dates(self, today: Date) -> Range<Date> {
+        match self {
+            Self::ThisWeek => {
+                let start = date::week_start(today);
+                start..date::add_days(start, 7)
+            }
+            Self::NextDays(days) => today..date::add_days(today, days.into()),
+            Self::Custom { from, until } => from..date::add_days(until, 1).max(from),
+        }
+    }
+
+    pub const fn kind(self) -> RangeKind {
+        match self {
+            Self::ThisWeek => RangeKind::ThisWeek,
+            Self::NextDays(_) => RangeKind::NextDays,
+            Self::Custom { .. } => RangeKind::Custom,
+        }
+    }
+
+    /// Switches to a range of `kind`, a custom range starts out as the days currently covered
+    pub fn with_kind(self, kind: RangeKind, today: Date) -> Self {
+        match kind {
+            _ if kind == self.kind() => self,
+            RangeKind::ThisWeek => Self::ThisWeek,
+            RangeKind::NextDays => Self::NextDays(7),
+            RangeKind::Custom => {
+                let Range { start, end } = self.dates(today);
+                Self::Custom {
+                    from: start,
+                    until: end.pred_opt().unwrap_or(end).max(start),
+                }
+            }
+        }
+    }
+}
+
+/// Ingrediants ticked off the shopping list, kept for each range of dates a list was made for
+pub type CheckedItems = BTreeMap<(Date, Date), BTreeSet<IngrediantKey>>;
+
+/// Ticks `ingrediant` on or off the list for `dates`. Lists for dates that are already over are
+/// forgotten.
+pub fn set_checked(
+    checked: &mut CheckedItems,
+    dates: Range<Date>,
+    ingrediant: IngrediantKey,
+    is_checked: bool,
+    today: Date,
+) {
+    checked.retain(|(_, until), _| *until > today);
+    let items = checked.entry((dates.start, dates.end)).or_default();
+    if is_checked {
+        items.insert(ingrediant);
+    } else {
+        items.remove(&ingrediant);
+        if items.is_empty() {
+            checked.remove(&(dates.start, dates.end));
+        }
+    }
+}
+
+/// How many portions of each meal are planned between `dates`
+pub fn planned_portions(model: &Model, dates: Range<Date>) -> BTreeMap<MealKey, u32> {
+    let mut portions = BTreeMap::new();
+    for (_, day) in model.days.range(dates) {
+        for planned in &day.meals {
+            *portions.entry(planned.meal_id).or_default() += planned.portions;
+        }
+    }
+    portions
+}
+
+/// Sums the ingrediants of every meal planned between `dates`, scaled from the
+/// recipe's servings to the portions planned
+pub fn shopping_list(model: &Model, dates: Range<Date>) -> ShoppingList {
+    let mut list = ShoppingList::new();
+    for (meal_id, portions) in planned_portions(model, dates) {
+        let Some(meal) = model.meals.get(meal_id) else {
+            continue;
+        };
+        let scale = meal.scale_for(portions);
+
+        for (ingrediant_id, IngrediantQuantity { quantity, unit }) in meal.ingrediants.iter() {
+            let (quantity, unit) = match model.ingrediants.get(*ingrediant_id) {
+                Some(ingrediant) => ingrediant.resolve(quantity * scale, *unit),
+                None => (quantity * scale, *unit),
+            };
+            let to_merge = list.entry(*ingrediant_id).or_default();
+            merge_into_unit(to_merge, quantity, unit, model.spoon_region);
+        }
+    }
+    for (ingrediant_id, merged) in list.iter_mut() {
+        if let Some(ingrediant) = model.ingrediants.get(*ingrediant_id) {
+            *merged = ingrediant
+                .conversions(model.spoon_region)
+                .fold(take(merged));
+        }
+    }
+    list
+}
+
+/// What is left of `list` to buy after using what is in the pantry. Pantry amounts are only taken
+/// off amounts they can be converted to, what is left over after one is taken off the next.
+pub fn shortfall(mut list: ShoppingList, model: &Model) -> ShoppingList {
+    for (ingrediant, IngrediantQuantity { quantity, unit }) in &model.pantry {
+        let Some(merged) = list.get_mut(ingrediant) else {
+            continue;
+        };
+        let (conversions, (quantity, unit)) = match model.ingrediants.get(*ingrediant) {
+            Some(ingrediant) => (
+                ingrediant.conversions(model.spoon_region),
+                ingrediant.resolve(*quantity, *unit),
+            ),
+            None => (
+                Conversions {
+                    spoons: model.spoon_region,
+                    ..Conversions::default()
+                },
+                (*quantity, *unit),
+            ),
+        };
+        let mut left = quantity;
+        for (base, needed) in merged.iter_mut() {
+            let Some(have) = conversions
+                .convert(left, unit, *base)
+                .filter(|have| *have > 0.0)
+            else {
+                continue;
+            };
+            let used = have.min(*needed);
+            *needed -= used;
+            left *= (have - used) / have;
+            if left <= 1e-9 {
+                break;
+            }
+        }
+        // Leave out amounts that are covered, allowing for rounding errors
+        merged.retain(|_, needed| *needed > 1e-9);
+        if merged.is_empty() {
+            list.remove(ingrediant);
+        }
+    }
+    list
+}
+
+/// The shopping list for `dates` less what is already in the pantry
+pub fn to_buy(model: &Model, dates: Range<Date>) -> ShoppingList {
+    shortfall(shopping_list(model, dates), model)
+}
+
+/// An ingrediant on the shopping list with its amounts in units that read well
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShoppingLine {
+    pub ingrediant: IngrediantKey,
+    pub name: Arc<str>,
+    pub category: Option<Arc<str>>,
+    pub ammounts: Vec<(f64, UnitChoice)>,
+}
+
+impl ShoppingLine {
+    /// The amounts joined up, e.g. "1.5 kg + 2 x"
+    pub fn ammounts_label(&self) -> String {
+        self.ammounts
+            .iter()
+            .map(|(ammount, unit)| format!("{} {unit}", round(*ammount)))
+            .join(" + ")
+    }
+}
+
+fn round(ammount: f64) -> f64 {
+    (ammount * 10.).round() / 10.
+}
+
+/// Names the ingrediants of `list` and picks units of `system` to show each amount in. Lines are
+/// sorted by aisle and then by name.
+pub fn lines(model: &Model, list: ShoppingList, system: UnitSystem) -> Vec<ShoppingLine> {
+    let mut lines: Vec<ShoppingLine> = list
+        .into_iter()
+        .filter_map(|(ingrediant, merged)| {
+            let found = model.ingrediants.get(ingrediant)?;
+            Some(ShoppingLine {
+                ingrediant,
+                name: found.name.clone(),
+                category: found.category.clone(),
+                ammounts: merged
+                    .into_iter()
+                    .map(|(unit, ammount)| {
+                        let (ammount, unit) = apropriate_unit(ammount, unit, system);
+                        (ammount, UnitChoice::new(Some(found), unit))
+                    })
+                    .collect(),
+            })
+        })
+        .collect();
+    lines.sort_by_cached_key(|line| {
+        (
+            aisles::rank(&model.aisles, line.category.as_deref()),
+            line.category.clone(),
+            line.name.to_lowercase(),
+        )
+    });
+    lines
+}
+
+/// Splits sorted `lines` into runs that share an aisle
+pub fn groups(lines: &[ShoppingLine]) -> impl Iterator<Item = &[ShoppingLine]> {
+    lines.chunk_by(|a, b| a.category == b.category)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    #[default]
+    Markdown,
+    Text,
+    Csv,
+}
+
+impl ExportFormat {
+    pub const ALL: [Self; 3] = [Self::Markdown, Self::Text, Self::Csv];
+
+    pub const fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Text => "txt",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "Markdown checklist",
+            Self::Text => "Plain text",
+            Self::Csv => "CSV",
+        })
+    }
+}
+
+/// Writes out the shopping list to be used outside the app
+pub fn export(lines: &[ShoppingLine], format: ExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        ExportFormat::Markdown => {
+            // Headings are only worth it once something has an aisle
+            let headings = lines.iter().any(|line| line.category.is_some());
+            for group in groups(lines) {
+                if headings {
+                    let aisle = group[0].category.as_deref().unwrap_or("Other");
+                    if !out.is_empty() {
+                        out.push('\n');
+                    }
+                    out.push_str(&format!("## {aisle}\n\n"));
+                }
+                for line in group {
+                    out.push_str(&format!("- [ ] {}: {}\n", line.name, line.ammounts_label()));
+                }
+            }
+        }
+        ExportFormat::Text => {
+            for line in lines {
+                out.push_str(&format!("{}: {}\n", line.name, line.ammounts_label()));
+            }
+        }
+        ExportFormat::Csv => {
+            out.push_str("name,amount,unit,category\n");
+            for line in lines {
+                for (ammount, unit) in &line.ammounts {
+                    out.push_str(&format!(
+                        "{},{},{},{}\n",
+                        csv_field(&line.name),
+                        round(*ammount),
+                        csv_field(&unit.to_string()),
+                        csv_field(line.category.as_deref().unwrap_or_default()),
+                    ));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Quotes a CSV field if it holds a separator, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+pub async fn write(path: String, contents: String) -> Result<(), String> {
+    async_std::fs::write(&path, contents)
+        .await
+        .map_err(|err| format!("couldn't write {path}: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        date,
+        ingrediant::Ingrediant,
+        meal::{Meal, PlannedMeal},
+        stable_id::StableId,
+        unit::{AmbiguosUnit, LiquidUnit, SolidUnit},
+        Day,
+    };
+
+    fn day(n: u64) -> Date {
+        date::add_days(Date::from_ymd_opt(2024, 10, 14).unwrap(), n)
+    }
+
+    fn plan(model: &mut Model, date: Date, meals: &[(MealKey, u32)]) {
+        let meals = meals
+            .iter()
+            .map(|&(meal_id, portions)| PlannedMeal { meal_id, portions })
+            .collect();
+        model.days.insert(date, Day { date, meals });
+    }
+
+    #[test]
+    fn scales_by_times_planned() {
+        let mut model = Model::default();
+        let mince = model.ingrediants.push(Ingrediant::new("Mince".into()));
+        let lasagne = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Lasagne".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([(
+                mince,
+                IngrediantQuantity {
+                    quantity: 500.0,
+                    unit: Unit::Solid(SolidUnit::Grams),
+                },
+            )]),
+        });
+        plan(&mut model, day(1), &[(lasagne, 1)]);
+        plan(&mut model, day(2), &[(lasagne, 1), (lasagne, 1)]);
+        plan(&mut model, day(9), &[(lasagne, 1)]);
+
+        assert_eq!(
+            shopping_list(&model, day(0)..day(7)),
+            BTreeMap::from([(
+                mince,
+                BTreeMap::from([(Unit::Solid(SolidUnit::Grams), 1500.0)])
+            )])
+        );
+    }
+
+    #[test]
+    fn adds_up_custom_units() {
+        let mut model = Model::default();
+        let mut garlic = Ingrediant::new("Garlic".into());
+        let clove = garlic.add_unit("clove").unwrap();
+        let bulb = garlic.add_unit("bulb").unwrap();
+        garlic.units[0].size = Some(5.0);
+        let garlic = model.ingrediants.push(garlic);
+        let meal = |quantity, unit| Meal {
+            id: StableId::new(),
+            name: "Curry".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([(garlic, IngrediantQuantity { quantity, unit })]),
+        };
+        let curry = model.meals.push(meal(3.0, clove));
+        let roast = model.meals.push(meal(1.0, bulb));
+        let stew = model.meals.push(meal(10.0, Unit::Solid(SolidUnit::Grams)));
+        plan(&mut model, day(1), &[(curry, 1), (roast, 1), (stew, 1)]);
+
+        let list = shopping_list(&model, day(0)..day(7));
+        assert_eq!(
+            list,
+            BTreeMap::from([(
+                garlic,
+                BTreeMap::from([(Unit::Solid(SolidUnit::Grams), 25.0), (bulb, 1.0)])
+            )])
+        );
+        assert_eq!(
+            lines(&model, list, UnitSystem::Metric)[0].ammounts_label(),
+            "25 g + 1 bulb"
+        );
+    }
+
+    #[test]
+    fn merges_across_meals() {
+        let mut model = Model::default();
+        let egg = model.ingrediants.push(Ingrediant::new("Egg".into()));
+        let flour = model.ingrediants.push(Ingrediant::new("Flour".into()));
+        let pancakes = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Pancakes".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([
+                (
+                    egg,
+                    IngrediantQuantity {
+                        quantity: 2.0,
+                        unit: Unit::Ambigous(AmbiguosUnit::Count),
+                    },
+                ),
+                (
+                    flour,
+                    IngrediantQuantity {
+                        quantity: 0.25,
+                        unit: Unit::Solid(SolidUnit::KiloGrams),
+                    },
+                ),
+            ]),
+        });
+        let omelette = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Omelette".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([(
+                egg,
+                IngrediantQuantity {
+                    quantity: 3.0,
+                    unit: Unit::Ambigous(AmbiguosUnit::Count),
+                },
+            )]),
+        });
+        plan(&mut model, day(1), &[(pancakes, 1), (omelette, 1)]);
+        plan(&mut model, day(2), &[(pancakes, 1)]);
+
+        assert_eq!(
+            shopping_list(&model, day(0)..day(7)),
+            BTreeMap::from([
+                (
+                    egg,
+                    BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 7.0)])
+                ),
+                (
+                    flour,
+                    BTreeMap::from([(Unit::Solid(SolidUnit::Grams), 500.0)])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn scales_portions_by_servings() {
+        let mut model = Model::default();
+        let rice = model.ingrediants.push(Ingrediant::new("Rice".into()));
+        let risotto = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Risotto".into(),
+            servings: 4,
+            ingrediants: BTreeMap::from([(
+                rice,
+                IngrediantQuantity {
+                    quantity: 400.0,
+                    unit: Unit::Solid(SolidUnit::Grams),
+                },
+            )]),
+        });
+        plan(&mut model, day(1), &[(risotto, 2)]);
+        plan(&mut model, day(6), &[(risotto, 6)]);
+
+        assert_eq!(
+            shopping_list(&model, day(0)..day(7)),
+            BTreeMap::from([(
+                rice,
+                BTreeMap::from([(Unit::Solid(SolidUnit::Grams), 800.0)])
+            )])
+        );
+    }
+
+    #[test]
+    fn exports_each_format() {
+        let mut model = Model::default();
+        let egg = model
+            .ingrediants
+            .push(Ingrediant::new("Eggs, large".into()));
+        let flour = model.ingrediants.push(Ingrediant::new("Flour".into()));
+        let list = BTreeMap::from([
+            (
+                egg,
+                BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 6.0)]),
+            ),
+            (
+                flour,
+                BTreeMap::from([
+                    (Unit::Solid(SolidUnit::Grams), 1250.0),
+                    (Unit::Ambigous(AmbiguosUnit::Count), 2.0),
+                ]),
+            ),
+        ]);
+        let lines = lines(&model, list, UnitSystem::Metric);
+
+        assert_eq!(
+            export(&lines, ExportFormat::Markdown),
+            "- [ ] Eggs, large: 6 x\n- [ ] Flour: 1.3 kg + 2 x\n"
+        );
+        assert_eq!(
+            export(&lines, ExportFormat::Text),
+            "Eggs, large: 6 x\nFlour: 1.3 kg + 2 x\n"
+        );
+        assert_eq!(
+            export(&lines, ExportFormat::Csv),
+            "name,amount,unit,category\n\"Eggs, large\",6,x,\nFlour,1.3,kg,\nFlour,2,x,\n"
+        );
+    }
+
+    #[test]
+    fn sorted_by_aisle() {
+        let mut model = Model::default();
+        let mut ingrediant = |name: &str, category: Option<&str>| {
+            let mut ingrediant = Ingrediant::new(name.into());
+            ingrediant.category = category.map(Into::into);
+            let key = model.ingrediants.push(ingrediant);
+            (
+                key,
+                BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 1.0)]),
+            )
+        };
+        let list = BTreeMap::from([
+            ingrediant("Milk", Some("Dairy")),
+            ingrediant("candles", None),
+            ingrediant("Apples", Some("Produce")),
+            ingrediant("butter", Some("Dairy")),
+        ]);
+        let lines = lines(&model, list, UnitSystem::Metric);
+
+        let names: Vec<_> = lines.iter().map(|line| &*line.name).collect();
+        assert_eq!(names, vec!["Apples", "butter", "Milk", "candles"]);
+        assert_eq!(groups(&lines).count(), 3);
+        assert_eq!(
+            export(&lines, ExportFormat::Markdown),
+            "## Produce\n\n- [ ] Apples: 1 x\n\n## Dairy\n\n- [ ] butter: 1 x\n- [ ] Milk: 1 x\n\n## Other\n\n- [ ] candles: 1 x\n"
+        );
+    }
+
+    #[test]
+    fn checks_are_kept_per_range() {
+        let mut model = Model::default();
+        let egg = model.ingrediants.push(Ingrediant::new("Egg".into()));
+        let mut checked = CheckedItems::new();
+
+        set_checked(&mut checked, day(0)..day(7), egg, true, day(0));
+        set_checked(&mut checked, day(7)..day(14), egg, true, day(0));
+        assert_eq!(checked.len(), 2);
+
+        set_checked(&mut checked, day(7)..day(14), egg, false, day(0));
+        assert_eq!(checked.keys().collect::<Vec<_>>(), vec![&(day(0), day(7))]);
+
+        // Once the week is over its list is dropped
+        set_checked(&mut checked, day(7)..day(14), egg, true, day(8));
+        assert_eq!(checked.keys().collect::<Vec<_>>(), vec![&(day(7), day(14))]);
+    }
+
+    #[test]
+    fn pantry_is_taken_off() {
+        let mut model = Model::default();
+        let rice = model.ingrediants.push(Ingrediant::new("Rice".into()));
+        let oil = model.ingrediants.push(Ingrediant::new("Oil".into()));
+        let egg = model.ingrediants.push(Ingrediant::new("Egg".into()));
+        let list = BTreeMap::from([
+            (
+                rice,
+                BTreeMap::from([
+                    (Unit::Solid(SolidUnit::Grams), 1500.0),
+                    (Unit::Ambigous(AmbiguosUnit::Count), 1.0),
+                ]),
+            ),
+            (
+                oil,
+                BTreeMap::from([(Unit::Liquid(LiquidUnit::MilliLiters), 30.0)]),
+            ),
+            (
+                egg,
+                BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 6.0)]),
+            ),
+        ]);
+        model.pantry = BTreeMap::from([
+            (
+                rice,
+                IngrediantQuantity {
+                    quantity: 1.0,
+                    unit: Unit::Solid(SolidUnit::KiloGrams),
+                },
+            ),
+            (
+                oil,
+                IngrediantQuantity {
+                    quantity: 5.0,
+                    unit: Unit::Ambigous(AmbiguosUnit::TableSpoon),
+                },
+            ),
+            // Can't be taken off a count
+            (
+                egg,
+                IngrediantQuantity {
+                    quantity: 100.0,
+                    unit: Unit::Solid(SolidUnit::Grams),
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            shortfall(list, &model),
+            BTreeMap::from([
+                (
+                    rice,
+                    BTreeMap::from([
+                        (Unit::Solid(SolidUnit::Grams), 500.0),
+                        (Unit::Ambigous(AmbiguosUnit::Count), 1.0),
+                    ]),
+                ),
+                (
+                    egg,
+                    BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 6.0)]),
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn pantry_left_over_carries_on() {
+        let mut model = Model::default();
+        let mut onion = Ingrediant::new("Onion".into());
+        onion.piece_weight = Some(100.0);
+        let onion = model.ingrediants.push(onion);
+        let list = BTreeMap::from([(
+            onion,
+            BTreeMap::from([
+                (Unit::Solid(SolidUnit::Grams), 100.0),
+                (Unit::Ambigous(AmbiguosUnit::Count), 3.0),
+            ]),
+        )]);
+        model.pantry = BTreeMap::from([(
+            onion,
+            IngrediantQuantity {
+                quantity: 300.0,
+                unit: Unit::Solid(SolidUnit::Grams),
+            },
+        )]);
+
+        assert_eq!(
+            shortfall(list, &model),
+            BTreeMap::from([(
+                onion,
+                BTreeMap::from([(Unit::Ambigous(AmbiguosUnit::Count), 1.0)]),
+            )])
+        );
+    }
+
+    #[test]
+    fn range_dates() {
+        // A Wednesday
+        let today = day(2);
+        assert_eq!(ShoppingRange::ThisWeek.dates(today), day(0)..day(7));
+        assert_eq!(ShoppingRange::NextDays(3).dates(today), day(2)..day(5));
+        let custom = ShoppingRange::NextDays(3).with_kind(RangeKind::Custom, today);
+        assert_eq!(
+            custom,
+            ShoppingRange::Custom {
+                from: day(2),
+                until: day(4)
+            }
+        );
+        assert_eq!(custom.dates(today), day(2)..day(5));
+    }
+
+    #[test]
+    fn merges_units_by_density() {
+        let mut model = Model::default();
+        let mut milk = Ingrediant::new("Milk".into());
+        milk.density = Some(1.25);
+        let milk = model.ingrediants.push(milk);
+        let quantity = |quantity, unit| (milk, IngrediantQuantity { quantity, unit });
+        let pancakes = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Pancakes".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([quantity(250.0, Unit::Solid(SolidUnit::Grams))]),
+        });
+        let porridge = model.meals.push(Meal {
+            id: StableId::new(),
+            name: "Porridge".into(),
+            servings: 1,
+            ingrediants: BTreeMap::from([quantity(0.5, Unit::Liquid(LiquidUnit::Liters))]),
+        });
+        plan(&mut model, day(1), &[(pancakes, 1), (porridge, 1)]);
+
+        let milliliters = Unit::Liquid(LiquidUnit::MilliLiters);
+        assert_eq!(
+            shopping_list(&model, day(0)..day(7)),
+            BTreeMap::from([(milk, BTreeMap::from([(milliliters, 700.0)]))])
+        );
+
+        // A pantry amount by weight is taken off the volume
+        model
+            .pantry
+            .insert(milk, quantity(500.0, Unit::Solid(SolidUnit::Grams)).1);
+        assert_eq!(
+            to_buy(&model, day(0)..day(7)),
+            BTreeMap::from([(milk, BTreeMap::from([(milliliters, 300.0)]))])
+        );
+    }
+}
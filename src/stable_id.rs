@@ -0,0 +1,94 @@
+use std::{fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Identifies a meal or ingrediant across saves, exports and devices, unlike a `GenerationalKey`
+/// which is only meaningful inside the map it came from. Formatted like a version 4 UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StableId(u128);
+
+impl StableId {
+    pub fn new() -> Self {
+        let random = fastrand::u128(..);
+        // Set the version (4, random) and variant bits the way a UUID would
+        let random = (random & !(0xf << 76)) | (0x4 << 76);
+        Self((random & !(0x3 << 62)) | (0x2 << 62))
+    }
+}
+
+impl Default for StableId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for StableId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let id = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            id >> 96,
+            (id >> 80) & 0xffff,
+            (id >> 64) & 0xffff,
+            (id >> 48) & 0xffff,
+            id & 0xffff_ffff_ffff
+        )
+    }
+}
+
+impl FromStr for StableId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits: String = s.chars().filter(|c| *c != '-').collect();
+        if digits.len() != 32 {
+            return Err(format!("invalid id \"{s}\""));
+        }
+        u128::from_str_radix(&digits, 16)
+            .map(Self)
+            .map_err(|_| format!("invalid id \"{s}\""))
+    }
+}
+
+impl Serialize for StableId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for StableId {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let id = StableId::new();
+        let text = id.to_string();
+        assert_eq!(text.len(), 36);
+        assert_eq!(&text[14..15], "4");
+        assert!(matches!(&text[19..20], "8" | "9" | "a" | "b"));
+        assert_eq!(text.parse(), Ok(id));
+    }
+
+    #[test]
+    fn distinct() {
+        assert_ne!(StableId::new(), StableId::new());
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        assert!("not-an-id".parse::<StableId>().is_err());
+        assert!("0123456789abcdef0123456789abcdeg"
+            .parse::<StableId>()
+            .is_err());
+    }
+}
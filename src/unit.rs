@@ -1,4 +1,4 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display};
 
 use serde::{Deserialize, Serialize};
 
@@ -8,6 +8,8 @@ pub enum SolidUnit {
     Grams,
     KiloGrams,
     Pinch,
+    Ounces,
+    Pounds,
 }
 
 #[derive(PartialEq, Copy, PartialOrd, Ord, Eq, Debug, Clone, Default, Serialize, Deserialize)]
@@ -15,6 +17,11 @@ pub enum LiquidUnit {
     #[default]
     MilliLiters,
     Liters,
+    /// US customary, as are the other volumes below
+    FluidOunces,
+    Cups,
+    Pints,
+    Quarts,
 }
 
 #[derive(PartialEq, Copy, PartialOrd, Ord, Eq, Debug, Clone, Default, Serialize, Deserialize)]
@@ -30,6 +37,8 @@ pub enum Unit {
     Solid(SolidUnit),
     Liquid(LiquidUnit),
     Ambigous(AmbiguosUnit),
+    /// One of the ingrediant's own units, by its index in `Ingrediant::units`
+    Custom(u8),
 }
 
 impl Default for Unit {
@@ -47,8 +56,84 @@ pub static UNITS: &[Unit] = &[
     Unit::Ambigous(AmbiguosUnit::TeaSpoon),
     Unit::Ambigous(AmbiguosUnit::TableSpoon),
     Unit::Ambigous(AmbiguosUnit::Count),
+    Unit::Solid(SolidUnit::Ounces),
+    Unit::Solid(SolidUnit::Pounds),
+    Unit::Liquid(LiquidUnit::FluidOunces),
+    Unit::Liquid(LiquidUnit::Cups),
+    Unit::Liquid(LiquidUnit::Pints),
+    Unit::Liquid(LiquidUnit::Quarts),
 ];
 
+/// Which units amounts are shown in
+#[derive(PartialEq, Copy, Eq, Debug, Clone, Default, Serialize, Deserialize)]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    /// Saved as "Imperial" before it was named for the units it actually uses
+    #[serde(alias = "Imperial")]
+    UsCustomary,
+}
+
+impl UnitSystem {
+    pub const ALL: [Self; 2] = [Self::Metric, Self::UsCustomary];
+}
+
+/// How big a tablespoon is where the recipes come from
+#[derive(PartialEq, Copy, Eq, Debug, Clone, Default, Serialize, Deserialize)]
+pub enum SpoonRegion {
+    #[default]
+    Metric,
+    /// Australian tablespoons hold four teaspoons
+    Australian,
+}
+
+impl SpoonRegion {
+    pub const ALL: [Self; 2] = [Self::Metric, Self::Australian];
+
+    pub const fn tablespoon_ml(self) -> f64 {
+        match self {
+            Self::Metric => 15.0,
+            Self::Australian => 20.0,
+        }
+    }
+
+    /// `Unit::in_base_unit` with tablespoons of this size
+    pub const fn in_base_unit(self, unit: Unit) -> f64 {
+        match unit {
+            Unit::Ambigous(AmbiguosUnit::TableSpoon) => self.tablespoon_ml(),
+            _ => unit.in_base_unit(),
+        }
+    }
+}
+
+impl Display for SpoonRegion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Metric => "15 ml tablespoon",
+            Self::Australian => "20 ml tablespoon",
+        })
+    }
+}
+
+impl Display for UnitSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Metric => "Metric",
+            Self::UsCustomary => "US customary",
+        })
+    }
+}
+
+/// The units offered when entering amounts, those of `system` and the ones every system shares.
+/// `current` is always included so an amount entered in another system can still be shown.
+pub fn units_for(system: UnitSystem, current: Unit) -> Vec<Unit> {
+    UNITS
+        .iter()
+        .copied()
+        .filter(|unit| unit.system().is_none_or(|of| of == system) || *unit == current)
+        .collect()
+}
+
 impl SolidUnit {
     pub const fn in_grams(self) -> f64 {
         match self {
@@ -57,19 +142,72 @@ impl SolidUnit {
             // Self::TeaSpoon => 4.2,
             // Self::TableSpoon => 13.0,
             Self::Pinch => 0.3,
+            Self::Ounces => 28.349_523_125,
+            Self::Pounds => 453.592_37,
+        }
+    }
+}
+
+impl LiquidUnit {
+    pub const fn in_milliliters(self) -> f64 {
+        match self {
+            Self::MilliLiters => 1.0,
+            Self::Liters => 1000.0,
+            Self::FluidOunces => 29.573_529_562_5,
+            Self::Cups => 236.588_236_5,
+            Self::Pints => 473.176_473,
+            Self::Quarts => 946.352_946,
         }
     }
 }
 
 impl Unit {
-    // pub fn to_grams(self, quantity: f64) -> f64 {
-    //     quantity * self.in_grams()
-    // }
+    /// The unit that quantities of this unit are summed in, units that share a base can be merged
+    pub const fn base_unit(self) -> Self {
+        match self {
+            Self::Solid(_) => Self::Solid(SolidUnit::Grams),
+            Self::Liquid(_) | Self::Ambigous(AmbiguosUnit::TeaSpoon | AmbiguosUnit::TableSpoon) => {
+                Self::Liquid(LiquidUnit::MilliLiters)
+            }
+            Self::Ambigous(AmbiguosUnit::Count) | Self::Custom(_) => self,
+        }
+    }
+
+    /// How many of `self.base_unit()` one of this unit is, tablespoons are taken to be 15 ml
+    pub const fn in_base_unit(self) -> f64 {
+        match self {
+            Self::Solid(solid) => solid.in_grams(),
+            Self::Liquid(liquid) => liquid.in_milliliters(),
+            Self::Ambigous(AmbiguosUnit::TeaSpoon) => 5.0,
+            Self::Ambigous(AmbiguosUnit::TableSpoon) => SpoonRegion::Metric.tablespoon_ml(),
+            Self::Ambigous(AmbiguosUnit::Count) | Self::Custom(_) => 1.0,
+        }
+    }
+
+    pub fn to_base_unit(self, quantity: f64) -> f64 {
+        quantity * self.in_base_unit()
+    }
+
+    pub fn convert_from_base_unit(self, quantity: f64) -> f64 {
+        quantity / self.in_base_unit()
+    }
 
     pub const fn is_liquid(self) -> bool {
+        !matches!(self, Self::Solid(_) | Self::Custom(_))
+    }
+
+    /// The system the unit belongs to, `None` for units used in both
+    pub const fn system(self) -> Option<UnitSystem> {
         match self {
-            Self::Solid(_) => false,
-            _ => true,
+            Self::Solid(SolidUnit::Grams | SolidUnit::KiloGrams)
+            | Self::Liquid(LiquidUnit::MilliLiters | LiquidUnit::Liters) => {
+                Some(UnitSystem::Metric)
+            }
+            Self::Solid(SolidUnit::Ounces | SolidUnit::Pounds)
+            | Self::Liquid(
+                LiquidUnit::FluidOunces | LiquidUnit::Cups | LiquidUnit::Pints | LiquidUnit::Quarts,
+            ) => Some(UnitSystem::UsCustomary),
+            Self::Solid(SolidUnit::Pinch) | Self::Ambigous(_) | Self::Custom(_) => None,
         }
     }
 
@@ -82,30 +220,122 @@ impl Unit {
             Self::Solid(SolidUnit::Grams) => "g",
             Self::Solid(SolidUnit::KiloGrams) => "kg",
             Self::Solid(SolidUnit::Pinch) => "pinch",
+            Self::Solid(SolidUnit::Ounces) => "oz",
+            Self::Solid(SolidUnit::Pounds) => "lb",
 
             Self::Liquid(LiquidUnit::MilliLiters) => "ml",
             Self::Liquid(LiquidUnit::Liters) => "L",
+            Self::Liquid(LiquidUnit::FluidOunces) => "fl oz",
+            Self::Liquid(LiquidUnit::Cups) => "cup",
+            Self::Liquid(LiquidUnit::Pints) => "pt",
+            Self::Liquid(LiquidUnit::Quarts) => "qt",
 
             Self::Ambigous(AmbiguosUnit::Count) => "x",
             Self::Ambigous(AmbiguosUnit::TeaSpoon) => "tsp",
             Self::Ambigous(AmbiguosUnit::TableSpoon) => "tbsp",
+
+            // Named by the ingrediant, see `Ingrediant::unit_label`
+            Self::Custom(_) => "unit",
         }
     }
 }
 
-pub fn apropriate_unit(grams: f64, is_liquid: bool) -> (f64, Unit) {
-    // if is_liquid {
-    //     if grams < Unit::Liters.in_grams() {
-    //         (grams, Unit::MilliLiters)
-    //     } else {
-    //         (Unit::Liters.grams(grams), Unit::Liters)
-    //     }
-    // } else if grams < Unit::KiloGrams.in_grams() {
-    //     (grams, Unit::Grams)
-    // } else {
-    //     (Unit::KiloGrams.grams(grams), Unit::KiloGrams)
-    // }
-    todo!()
+/// Picks a readable unit of `system` for `quantity` measured in `base`, e.g. 1200 g becomes 1.2 kg
+/// or 2.6 lb
+pub fn apropriate_unit(quantity: f64, base: Unit, system: UnitSystem) -> (f64, Unit) {
+    // Each unit is used from when the amount reaches one of it
+    let steps: &[Unit] = match (base.base_unit(), system) {
+        (Unit::Solid(_), UnitSystem::Metric) => &[
+            Unit::Solid(SolidUnit::Grams),
+            Unit::Solid(SolidUnit::KiloGrams),
+        ],
+        (Unit::Solid(_), UnitSystem::UsCustomary) => &[
+            Unit::Solid(SolidUnit::Ounces),
+            Unit::Solid(SolidUnit::Pounds),
+        ],
+        (Unit::Liquid(_), UnitSystem::Metric) => &[
+            Unit::Liquid(LiquidUnit::MilliLiters),
+            Unit::Liquid(LiquidUnit::Liters),
+        ],
+        (Unit::Liquid(_), UnitSystem::UsCustomary) => &[
+            Unit::Liquid(LiquidUnit::FluidOunces),
+            Unit::Liquid(LiquidUnit::Cups),
+            Unit::Liquid(LiquidUnit::Quarts),
+        ],
+        (Unit::Ambigous(_) | Unit::Custom(_), _) => return (quantity, base),
+    };
+    let in_base = base.to_base_unit(quantity);
+    let unit = steps
+        .iter()
+        .rev()
+        .find(|unit| in_base >= unit.in_base_unit())
+        .unwrap_or(&steps[0]);
+    (unit.convert_from_base_unit(in_base), *unit)
+}
+
+/// Adds `quantity` of `unit` into `merged`, which holds totals keyed by base unit.
+/// Quantities whose units can't be converted into each other are kept separate.
+pub fn merge_into_unit(
+    merged: &mut BTreeMap<Unit, f64>,
+    quantity: f64,
+    unit: Unit,
+    spoons: SpoonRegion,
+) {
+    *merged.entry(unit.base_unit()).or_default() += quantity * spoons.in_base_unit(unit);
+}
+
+/// What an ingrediant knows about relating its units, beyond the fixed factors between units
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Conversions {
+    /// Grams per millilitre
+    pub density: Option<f64>,
+    /// Grams one of it weighs
+    pub piece_weight: Option<f64>,
+    pub spoons: SpoonRegion,
+}
+
+impl Conversions {
+    /// How many grams one of `base` weighs, if it is known
+    fn grams_per(self, base: Unit) -> Option<f64> {
+        match base {
+            Unit::Solid(_) => Some(1.0),
+            Unit::Liquid(_) => self.density,
+            Unit::Ambigous(AmbiguosUnit::Count) => self.piece_weight,
+            Unit::Ambigous(_) | Unit::Custom(_) => None,
+        }
+        .filter(|grams| *grams > 0.0)
+    }
+
+    /// `quantity` of `from` in `to`, if one can be converted to the other
+    pub fn convert(self, quantity: f64, from: Unit, to: Unit) -> Option<f64> {
+        let in_base = quantity * self.spoons.in_base_unit(from);
+        let in_base = if from.base_unit() == to.base_unit() {
+            in_base
+        } else {
+            in_base * self.grams_per(from.base_unit())? / self.grams_per(to.base_unit())?
+        };
+        Some(in_base / self.spoons.in_base_unit(to))
+    }
+
+    /// Folds totals kept per base unit together where this ingrediant's density or piece weight
+    /// relate them. They are added up in whichever unit held the most.
+    pub fn fold(self, merged: BTreeMap<Unit, f64>) -> BTreeMap<Unit, f64> {
+        let (convertible, mut folded): (BTreeMap<_, _>, BTreeMap<_, _>) = merged
+            .into_iter()
+            .partition(|(unit, _)| self.grams_per(*unit).is_some());
+        let grams =
+            |(unit, quantity): (&Unit, &f64)| quantity * self.grams_per(*unit).unwrap_or(0.0);
+        let Some(target) = convertible
+            .iter()
+            .max_by(|a, b| grams(*a).total_cmp(&grams(*b)))
+            .map(|(unit, _)| *unit)
+        else {
+            return folded;
+        };
+        let total = convertible.iter().map(grams).sum::<f64>();
+        folded.insert(target, total / self.grams_per(target).unwrap_or(1.0));
+        folded
+    }
 }
 
 impl Display for Unit {
@@ -113,3 +343,186 @@ impl Display for Unit {
         write!(f, "{}", self.abreviation())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_converts_compatible_units() {
+        let mut merged = BTreeMap::new();
+        let spoons = SpoonRegion::Metric;
+        merge_into_unit(&mut merged, 1.0, Unit::Solid(SolidUnit::KiloGrams), spoons);
+        merge_into_unit(&mut merged, 200.0, Unit::Solid(SolidUnit::Grams), spoons);
+        merge_into_unit(&mut merged, 0.5, Unit::Liquid(LiquidUnit::Liters), spoons);
+        merge_into_unit(
+            &mut merged,
+            250.0,
+            Unit::Liquid(LiquidUnit::MilliLiters),
+            spoons,
+        );
+        assert_eq!(
+            merged,
+            BTreeMap::from([
+                (Unit::Solid(SolidUnit::Grams), 1200.0),
+                (Unit::Liquid(LiquidUnit::MilliLiters), 750.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn spoons_are_volumes() {
+        let tablespoon = Unit::Ambigous(AmbiguosUnit::TableSpoon);
+        let mut merged = BTreeMap::new();
+        merge_into_unit(&mut merged, 1.0, tablespoon, SpoonRegion::Metric);
+        merge_into_unit(
+            &mut merged,
+            30.0,
+            Unit::Liquid(LiquidUnit::MilliLiters),
+            SpoonRegion::Metric,
+        );
+        merge_into_unit(
+            &mut merged,
+            1.0,
+            Unit::Ambigous(AmbiguosUnit::TeaSpoon),
+            SpoonRegion::Metric,
+        );
+        merge_into_unit(&mut merged, 1.0, tablespoon, SpoonRegion::Australian);
+        assert_eq!(
+            merged,
+            BTreeMap::from([(Unit::Liquid(LiquidUnit::MilliLiters), 70.0)])
+        );
+
+        let australian = Conversions {
+            spoons: SpoonRegion::Australian,
+            ..Conversions::default()
+        };
+        assert_eq!(
+            australian.convert(2.0, tablespoon, Unit::Ambigous(AmbiguosUnit::TeaSpoon)),
+            Some(8.0)
+        );
+    }
+
+    #[test]
+    fn merge_keeps_incompatible_units_separate() {
+        let mut merged = BTreeMap::new();
+        let spoons = SpoonRegion::Metric;
+        merge_into_unit(
+            &mut merged,
+            3.0,
+            Unit::Ambigous(AmbiguosUnit::Count),
+            spoons,
+        );
+        merge_into_unit(&mut merged, 100.0, Unit::Solid(SolidUnit::Grams), spoons);
+        merge_into_unit(
+            &mut merged,
+            2.0,
+            Unit::Ambigous(AmbiguosUnit::Count),
+            spoons,
+        );
+        assert_eq!(
+            merged,
+            BTreeMap::from([
+                (Unit::Solid(SolidUnit::Grams), 100.0),
+                (Unit::Ambigous(AmbiguosUnit::Count), 5.0),
+            ])
+        );
+    }
+
+    #[test]
+    fn apropriate_unit_scales_up() {
+        let metric = UnitSystem::Metric;
+        assert_eq!(
+            apropriate_unit(1200.0, Unit::Solid(SolidUnit::Grams), metric),
+            (1.2, Unit::Solid(SolidUnit::KiloGrams))
+        );
+        assert_eq!(
+            apropriate_unit(999.0, Unit::Solid(SolidUnit::Grams), metric),
+            (999.0, Unit::Solid(SolidUnit::Grams))
+        );
+        assert_eq!(
+            apropriate_unit(1500.0, Unit::Liquid(LiquidUnit::MilliLiters), metric),
+            (1.5, Unit::Liquid(LiquidUnit::Liters))
+        );
+        assert_eq!(
+            apropriate_unit(4.0, Unit::Ambigous(AmbiguosUnit::TableSpoon), metric),
+            (60.0, Unit::Liquid(LiquidUnit::MilliLiters))
+        );
+        assert_eq!(
+            apropriate_unit(4.0, Unit::Ambigous(AmbiguosUnit::Count), metric),
+            (4.0, Unit::Ambigous(AmbiguosUnit::Count))
+        );
+    }
+
+    #[test]
+    fn apropriate_unit_us_customary() {
+        let us = UnitSystem::UsCustomary;
+        assert_eq!(
+            apropriate_unit(2.0, Unit::Solid(SolidUnit::Pounds), us),
+            (2.0, Unit::Solid(SolidUnit::Pounds))
+        );
+        let (ounces, unit) = apropriate_unit(100.0, Unit::Solid(SolidUnit::Grams), us);
+        assert_eq!(unit, Unit::Solid(SolidUnit::Ounces));
+        assert!((ounces - 3.527).abs() < 0.001);
+        assert_eq!(
+            apropriate_unit(4.0, Unit::Liquid(LiquidUnit::Pints), us),
+            (2.0, Unit::Liquid(LiquidUnit::Quarts))
+        );
+        assert_eq!(
+            apropriate_unit(8.0, Unit::Liquid(LiquidUnit::FluidOunces), us),
+            (1.0, Unit::Liquid(LiquidUnit::Cups))
+        );
+        // And back again
+        assert_eq!(
+            apropriate_unit(2.0, Unit::Solid(SolidUnit::Pounds), UnitSystem::Metric),
+            (907.18474, Unit::Solid(SolidUnit::Grams))
+        );
+        // Older saves call it imperial
+        assert_eq!(
+            serde_json::from_str::<UnitSystem>(r#""Imperial""#).unwrap(),
+            us
+        );
+    }
+
+    #[test]
+    fn folds_with_density_and_piece_weight() {
+        let milk = Conversions {
+            density: Some(1.25),
+            ..Conversions::default()
+        };
+        let merged = BTreeMap::from([
+            (Unit::Solid(SolidUnit::Grams), 250.0),
+            (Unit::Liquid(LiquidUnit::MilliLiters), 500.0),
+            (Unit::Ambigous(AmbiguosUnit::Count), 1.0),
+        ]);
+        assert_eq!(
+            milk.fold(merged.clone()),
+            BTreeMap::from([
+                (Unit::Liquid(LiquidUnit::MilliLiters), 700.0),
+                (Unit::Ambigous(AmbiguosUnit::Count), 1.0),
+            ])
+        );
+        assert_eq!(Conversions::default().fold(merged.clone()), merged);
+
+        let egg = Conversions {
+            piece_weight: Some(60.0),
+            ..Conversions::default()
+        };
+        assert_eq!(
+            egg.convert(
+                0.12,
+                Unit::Solid(SolidUnit::KiloGrams),
+                Unit::Ambigous(AmbiguosUnit::Count)
+            ),
+            Some(2.0)
+        );
+        assert_eq!(
+            egg.convert(
+                1.0,
+                Unit::Liquid(LiquidUnit::Liters),
+                Unit::Solid(SolidUnit::Grams)
+            ),
+            None
+        );
+    }
+}